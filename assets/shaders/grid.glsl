@@ -0,0 +1,22 @@
+varying vec2 v_world_pos;
+
+#ifdef VERTEX_SHADER
+attribute vec2 a_pos;
+uniform mat3 u_view_matrix;
+uniform mat3 u_projection_matrix;
+void main() {
+    vec2 pos = a_pos * 2.0 - 1.0;
+    v_world_pos = (inverse(u_projection_matrix * u_view_matrix) * vec3(pos, 1.0)).xy;
+    gl_Position = vec4(pos, 0.0, 1.0);
+}
+#endif
+
+#ifdef FRAGMENT_SHADER
+uniform float u_spacing;
+void main() {
+    vec2 cell = v_world_pos / u_spacing;
+    vec2 dist = abs(fract(cell - 0.5) - 0.5) / fwidth(cell);
+    float line = 1.0 - min(min(dist.x, dist.y), 1.0);
+    gl_FragColor = vec4(1.0, 1.0, 1.0, 1.0) * line * 0.3;
+}
+#endif
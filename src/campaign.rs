@@ -0,0 +1,344 @@
+use super::*;
+
+#[derive(Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Unlock {
+    /// Playable from the start
+    Always,
+    /// Playable once the previous level of the campaign is completed
+    #[default]
+    Previous,
+    /// Playable once all of the listed levels are completed
+    Levels(Vec<String>),
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(from = "LevelEntry")]
+pub struct LevelInfo {
+    /// File name without extension
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hint: Option<String>,
+    /// Seconds
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub par_time: Option<f32>,
+    /// Path relative to the pack directory, keeps playing the current track if missing
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub music: Option<String>,
+    pub unlock: Unlock,
+}
+
+impl LevelInfo {
+    pub fn new(name: String) -> Self {
+        Self {
+            name,
+            title: None,
+            author: None,
+            hint: None,
+            par_time: None,
+            music: None,
+            unlock: Unlock::Previous,
+        }
+    }
+
+    pub fn title(&self) -> &str {
+        self.title.as_deref().unwrap_or(&self.name)
+    }
+}
+
+/// Levels can be listed by name only
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum LevelEntry {
+    Name(String),
+    Info {
+        name: String,
+        #[serde(default)]
+        title: Option<String>,
+        #[serde(default)]
+        author: Option<String>,
+        #[serde(default)]
+        hint: Option<String>,
+        #[serde(default)]
+        par_time: Option<f32>,
+        #[serde(default)]
+        music: Option<String>,
+        #[serde(default)]
+        unlock: Unlock,
+    },
+}
+
+impl From<LevelEntry> for LevelInfo {
+    fn from(entry: LevelEntry) -> Self {
+        match entry {
+            LevelEntry::Name(name) => Self::new(name),
+            LevelEntry::Info {
+                name,
+                title,
+                author,
+                hint,
+                par_time,
+                music,
+                unlock,
+            } => Self {
+                name,
+                title,
+                author,
+                hint,
+                par_time,
+                music,
+                unlock,
+            },
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Campaign {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// Campaigns that have to be completed before this one is playable
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub requires: Vec<String>,
+    pub levels: Vec<LevelInfo>,
+}
+
+impl Campaign {
+    pub fn title(&self) -> &str {
+        self.title.as_deref().unwrap_or(&self.name)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Manifest {
+    pub campaigns: Vec<Campaign>,
+}
+
+impl Manifest {
+    pub const FILE_NAME: &'static str = "_manifest.json";
+    /// Bare list of level names used before the manifest
+    pub const LEGACY_FILE_NAME: &'static str = "_list.json";
+
+    pub async fn load(dir: &std::path::Path) -> anyhow::Result<Self> {
+        let path = dir.join(Self::FILE_NAME);
+        let error = match file::load_json::<Self>(&path).await {
+            Ok(manifest) => {
+                manifest.validate()?;
+                return Ok(manifest);
+            }
+            Err(e) => e,
+        };
+        // Only packs from before the manifest fall back to the list, a broken manifest is an error
+        #[cfg(not(target_arch = "wasm32"))]
+        if path.exists() {
+            return Err(error);
+        }
+        let Ok(list) = file::load_json::<Vec<String>>(dir.join(Self::LEGACY_FILE_NAME)).await
+        else {
+            return Err(error);
+        };
+        let manifest = Self {
+            campaigns: vec![Campaign {
+                name: "main".to_owned(),
+                title: None,
+                requires: Vec::new(),
+                levels: list.into_iter().map(LevelInfo::new).collect(),
+            }],
+        };
+        manifest.validate()?;
+        Ok(manifest)
+    }
+
+    /// Checks what the rest of the game relies on
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.campaigns.is_empty() {
+            anyhow::bail!("manifest has no campaigns");
+        }
+        let mut names = HashSet::new();
+        for level in self.campaigns.iter().flat_map(|campaign| &campaign.levels) {
+            if !names.insert(level.name.as_str()) {
+                anyhow::bail!("level {:?} is listed more than once", level.name);
+            }
+        }
+        for campaign in &self.campaigns {
+            for level in &campaign.levels {
+                if let Unlock::Levels(required) = &level.unlock {
+                    if let Some(unknown) =
+                        required.iter().find(|name| !names.contains(name.as_str()))
+                    {
+                        anyhow::bail!(
+                            "level {:?} is unlocked by unknown level {unknown:?}",
+                            level.name
+                        );
+                    }
+                }
+            }
+            if campaign.levels.is_empty() {
+                anyhow::bail!("campaign {:?} has no levels", campaign.name);
+            }
+            for required in &campaign.requires {
+                if !self.campaigns.iter().any(|other| other.name == *required) {
+                    anyhow::bail!(
+                        "campaign {:?} requires unknown campaign {required:?}",
+                        campaign.name,
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn music_tracks(&self) -> impl Iterator<Item = &str> {
+        self.campaigns
+            .iter()
+            .flat_map(|campaign| &campaign.levels)
+            .filter_map(|level| level.music.as_deref())
+    }
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct Progress {
+    /// Keys are made with `Progress::key`
+    pub completed: HashSet<String>,
+    /// Best time in seconds
+    pub best_times: HashMap<String, f32>,
+}
+
+impl Progress {
+    const KEY: &'static str = "progress";
+
+    pub fn load() -> Self {
+        preferences::load(Self::KEY).unwrap_or_default()
+    }
+
+    pub fn save(&self) {
+        preferences::save(Self::KEY, self);
+    }
+
+    /// Level names are only unique within a pack
+    pub fn key(pack: &str, level: &str) -> String {
+        format!("{pack}/{level}")
+    }
+
+    pub fn complete(&mut self, key: String, time: f32) {
+        let best = self.best_times.entry(key.clone()).or_insert(time);
+        *best = best.min(time);
+        self.completed.insert(key);
+        self.save();
+    }
+
+    /// Keeps the progress of a renamed level
+    pub fn rename(&mut self, from: &str, to: String) {
+        if self.completed.remove(from) {
+            self.completed.insert(to.clone());
+        }
+        if let Some(time) = self.best_times.remove(from) {
+            self.best_times.insert(to, time);
+        }
+        self.save();
+    }
+
+    fn is_completed(&self, pack: &str, level: &str) -> bool {
+        self.completed.contains(&Self::key(pack, level))
+    }
+
+    fn campaign_completed(&self, pack: &str, campaign: &Campaign) -> bool {
+        campaign
+            .levels
+            .iter()
+            .all(|level| self.is_completed(pack, &level.name))
+    }
+
+    pub fn is_unlocked(
+        &self,
+        pack: &str,
+        manifest: &Manifest,
+        campaign: usize,
+        level: usize,
+    ) -> bool {
+        let campaign = &manifest.campaigns[campaign];
+        let campaign_unlocked = campaign.requires.iter().all(|required| {
+            manifest
+                .campaigns
+                .iter()
+                .filter(|other| &other.name == required)
+                .all(|other| self.campaign_completed(pack, other))
+        });
+        if !campaign_unlocked {
+            return false;
+        }
+        match &campaign.levels[level].unlock {
+            Unlock::Always => true,
+            Unlock::Previous => {
+                level == 0 || self.is_completed(pack, &campaign.levels[level - 1].name)
+            }
+            Unlock::Levels(levels) => levels.iter().all(|name| self.is_completed(pack, name)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manifest(json: &str) -> Manifest {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn valid() {
+        let manifest = manifest(
+            r#"{"campaigns": [
+                {"name": "main", "levels": ["a", {"name": "b", "unlock": "always"}]},
+                {"name": "bonus", "requires": ["main"], "levels": ["c"]}
+            ]}"#,
+        );
+        assert!(manifest.validate().is_ok());
+    }
+
+    #[test]
+    fn empty_rejected() {
+        assert!(manifest(r#"{"campaigns": []}"#).validate().is_err());
+        assert!(
+            manifest(r#"{"campaigns": [{"name": "main", "levels": []}]}"#)
+                .validate()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn unknown_requirement_rejected() {
+        let manifest = manifest(
+            r#"{"campaigns": [{"name": "bonus", "requires": ["main"], "levels": ["c"]}]}"#,
+        );
+        assert!(manifest.validate().is_err());
+    }
+
+    #[test]
+    fn bad_level_names_rejected() {
+        let duplicate = manifest(
+            r#"{"campaigns": [
+                {"name": "main", "levels": ["a", "b"]},
+                {"name": "bonus", "levels": ["a"]}
+            ]}"#,
+        );
+        assert!(duplicate.validate().is_err());
+        let unknown_unlock = manifest(
+            r#"{"campaigns": [
+                {"name": "main", "levels": ["a", {"name": "b", "unlock": {"levels": ["c"]}}]}
+            ]}"#,
+        );
+        assert!(unknown_unlock.validate().is_err());
+        let unlock = manifest(
+            r#"{"campaigns": [
+                {"name": "main", "levels": ["a", {"name": "b", "unlock": {"levels": ["a"]}}]}
+            ]}"#,
+        );
+        assert!(unlock.validate().is_ok());
+    }
+}
@@ -0,0 +1,364 @@
+use super::*;
+
+/// Ends closer than this are the same vertex
+const COINCIDENT_DISTANCE: f32 = 1e-4;
+
+#[derive(Debug, Clone)]
+pub struct Options {
+    /// Snaps endpoints closer than `merge_distance` together before looking for crossings
+    pub merge: bool,
+    pub merge_distance: f32,
+    /// Splits surfaces where they cross or where another surface ends on them,
+    /// overlapping collinear surfaces keep the shared part once
+    pub split: bool,
+    /// Removes chains of surfaces that have a free end
+    pub remove_dangling: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            merge: true,
+            merge_distance: 0.05,
+            split: true,
+            remove_dangling: false,
+        }
+    }
+}
+
+/// Result of a clean up, shown as a preview before it is applied
+pub struct Plan {
+    pub level: Level,
+    /// Where endpoints were moved to
+    pub merged: Vec<vec2<f32>>,
+    pub splits: Vec<vec2<f32>>,
+    /// Dangling surfaces, repeated overlaps and the ones merging made zero length
+    pub removed: Vec<Surface>,
+}
+
+impl Plan {
+    pub fn is_empty(&self) -> bool {
+        self.merged.is_empty() && self.splits.is_empty() && self.removed.is_empty()
+    }
+}
+
+impl std::fmt::Display for Plan {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "merge {} endpoints, split at {} points, remove {} surfaces",
+            self.merged.len(),
+            self.splits.len(),
+            self.removed.len(),
+        )
+    }
+}
+
+fn same(a: vec2<f32>, b: vec2<f32>) -> bool {
+    (a - b).len() < COINCIDENT_DISTANCE
+}
+
+fn same_surface(first: &Surface, second: &Surface) -> bool {
+    let [a, b] = first.ends;
+    let [c, d] = second.ends;
+    (same(a, c) && same(b, d)) || (same(a, d) && same(b, c))
+}
+
+/// Distance from the line through the surface
+fn line_distance(surface: &Surface, p: vec2<f32>) -> f32 {
+    let [a, b] = surface.ends;
+    vec2::skew((b - a).normalize_or_zero(), p - a).abs()
+}
+
+fn collinear(first: &Surface, second: &Surface) -> bool {
+    first.ends.iter().chain(&second.ends).all(|&p| {
+        line_distance(first, p) < COINCIDENT_DISTANCE
+            && line_distance(second, p) < COINCIDENT_DISTANCE
+    })
+}
+
+/// The point projected onto the surface, if it falls strictly between the ends
+fn project_inside(surface: &Surface, p: vec2<f32>) -> Option<vec2<f32>> {
+    let [a, b] = surface.ends;
+    let t = vec2::dot(p - a, b - a) / vec2::dot(b - a, b - a);
+    let pos = a + (b - a) * t;
+    (t > 0.0 && t < 1.0 && !same(pos, a) && !same(pos, b)).then_some(pos)
+}
+
+fn push_unique(points: &mut Vec<vec2<f32>>, p: vec2<f32>) {
+    if !points.iter().any(|&other| same(other, p)) {
+        points.push(p);
+    }
+}
+
+pub fn plan(level: &Level, options: &Options) -> Plan {
+    let mut plan = Plan {
+        level: level.clone(),
+        merged: Vec::new(),
+        splits: Vec::new(),
+        removed: Vec::new(),
+    };
+    if options.merge {
+        merge_endpoints(&mut plan, options.merge_distance);
+    }
+    if options.split {
+        split_surfaces(&mut plan);
+    }
+    if options.remove_dangling {
+        remove_dangling(&mut plan);
+    }
+    plan
+}
+
+fn merge_endpoints(plan: &mut Plan, distance: f32) {
+    let mut anchors: Vec<vec2<f32>> = Vec::new();
+    for surface in &mut plan.level.surfaces {
+        for end in &mut surface.ends {
+            match anchors
+                .iter()
+                .find(|anchor| (**anchor - *end).len() < distance)
+            {
+                Some(&anchor) => {
+                    if !same(anchor, *end) {
+                        push_unique(&mut plan.merged, anchor);
+                    }
+                    *end = anchor;
+                }
+                None => anchors.push(*end),
+            }
+        }
+    }
+    let (degenerate, kept): (Vec<Surface>, Vec<Surface>) = std::mem::take(&mut plan.level.surfaces)
+        .into_iter()
+        .partition(|surface| same(surface.ends[0], surface.ends[1]));
+    plan.level.surfaces = kept;
+    plan.removed = degenerate;
+}
+
+fn split_surfaces(plan: &mut Plan) {
+    let surfaces = &plan.level.surfaces;
+    // Points to split each surface at, found once per pair so that both halves share them exactly
+    let mut cuts: Vec<Vec<vec2<f32>>> = vec![Vec::new(); surfaces.len()];
+    for (i, first) in surfaces.iter().enumerate() {
+        for (j, second) in surfaces.iter().enumerate().skip(i + 1) {
+            if collinear(first, second) {
+                // Overlap, cut each surface where the other one ends
+                for (index, surface, other) in [(i, first, second), (j, second, first)] {
+                    for pos in other
+                        .ends
+                        .iter()
+                        .filter_map(|&end| project_inside(surface, end))
+                    {
+                        cuts[index].push(pos);
+                        push_unique(&mut plan.splits, pos);
+                    }
+                }
+            } else if let Some(pos) = validate::segment_intersection(first.ends, second.ends) {
+                cuts[i].push(pos);
+                cuts[j].push(pos);
+                push_unique(&mut plan.splits, pos);
+            }
+        }
+        // T junctions, another surface ending in the middle of this one
+        for (j, other) in surfaces.iter().enumerate() {
+            for end in other.ends {
+                if j != i
+                    && !collinear(first, other)
+                    && !first.ends.iter().any(|&own| same(own, end))
+                    && first.to(end).distance < COINCIDENT_DISTANCE
+                {
+                    cuts[i].push(end);
+                    push_unique(&mut plan.splits, end);
+                }
+            }
+        }
+    }
+    let mut result: Vec<Surface> = Vec::new();
+    for (surface, mut cuts) in surfaces.iter().zip(cuts) {
+        let [a, b] = surface.ends;
+        cuts.sort_by_key(|&p| r32((p - a).len()));
+        cuts.dedup_by(|p, q| same(*p, *q));
+        let mut start = a;
+        for cut in cuts.into_iter().chain([b]) {
+            if !same(start, cut) {
+                let piece = Surface { ends: [start, cut] };
+                // Overlapping collinear surfaces both produce the shared part
+                if result.iter().any(|other| same_surface(other, &piece)) {
+                    plan.removed.push(piece);
+                } else {
+                    result.push(piece);
+                }
+            }
+            start = cut;
+        }
+    }
+    plan.level.surfaces = result;
+}
+
+/// Repeatedly removes surfaces with an end that no other surface shares
+fn remove_dangling(plan: &mut Plan) {
+    loop {
+        let surfaces = &plan.level.surfaces;
+        let free_end = |index: usize, end: vec2<f32>| {
+            !surfaces.iter().enumerate().any(|(other, surface)| {
+                other != index && surface.ends.iter().any(|&e| same(e, end))
+            })
+        };
+        let (dangling, kept): (Vec<_>, Vec<_>) = surfaces
+            .iter()
+            .enumerate()
+            .partition(|&(index, surface)| surface.ends.iter().any(|&end| free_end(index, end)));
+        if dangling.is_empty() {
+            return;
+        }
+        let kept = kept
+            .into_iter()
+            .map(|(_, surface)| surface.clone())
+            .collect();
+        plan.removed
+            .extend(dangling.into_iter().map(|(_, surface)| surface.clone()));
+        plan.level.surfaces = kept;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn level(surfaces: &[[(f32, f32); 2]]) -> Level {
+        Level {
+            start_pos: vec2::ZERO,
+            finish_pos: vec2(5.0, 0.0),
+            finish_radius: None,
+            surfaces: surfaces
+                .iter()
+                .map(|ends| Surface {
+                    ends: ends.map(|(x, y)| vec2(x, y)),
+                })
+                .collect(),
+        }
+    }
+
+    fn options(merge: bool, split: bool, remove_dangling: bool) -> Options {
+        Options {
+            merge,
+            split,
+            remove_dangling,
+            ..default()
+        }
+    }
+
+    /// Surfaces as sorted pairs of sorted ends, so comparisons ignore order and direction
+    fn normalized(level: &Level) -> Vec<[(i64, i64); 2]> {
+        let key = |p: vec2<f32>| ((p.x * 1000.0).round() as i64, (p.y * 1000.0).round() as i64);
+        let mut surfaces: Vec<_> = level
+            .surfaces
+            .iter()
+            .map(|surface| {
+                let mut ends = surface.ends.map(key);
+                ends.sort();
+                ends
+            })
+            .collect();
+        surfaces.sort();
+        surfaces
+    }
+
+    #[test]
+    fn x_crossing_splits_both() {
+        let plan = plan(
+            &level(&[[(-1.0, -1.0), (1.0, 1.0)], [(-1.0, 1.0), (1.0, -1.0)]]),
+            &options(false, true, false),
+        );
+        assert_eq!(plan.splits.len(), 1);
+        assert!((plan.splits[0] - vec2::ZERO).len() < 1e-4);
+        assert_eq!(
+            normalized(&plan.level),
+            normalized(&level(&[
+                [(-1.0, -1.0), (0.0, 0.0)],
+                [(0.0, 0.0), (1.0, 1.0)],
+                [(-1.0, 1.0), (0.0, 0.0)],
+                [(0.0, 0.0), (1.0, -1.0)],
+            ])),
+        );
+    }
+
+    #[test]
+    fn t_junction_splits_the_crossbar() {
+        let plan = plan(
+            &level(&[[(-1.0, 0.0), (1.0, 0.0)], [(0.0, 0.0), (0.0, 1.0)]]),
+            &options(false, true, false),
+        );
+        assert_eq!(plan.splits.len(), 1);
+        assert_eq!(
+            normalized(&plan.level),
+            normalized(&level(&[
+                [(-1.0, 0.0), (0.0, 0.0)],
+                [(0.0, 0.0), (1.0, 0.0)],
+                [(0.0, 0.0), (0.0, 1.0)],
+            ])),
+        );
+    }
+
+    #[test]
+    fn collinear_overlap_kept_once() {
+        let overlap = plan(
+            &level(&[[(0.0, 0.0), (4.0, 0.0)], [(6.0, 0.0), (2.0, 0.0)]]),
+            &options(false, true, false),
+        );
+        assert_eq!(overlap.splits.len(), 2);
+        assert_eq!(overlap.removed.len(), 1);
+        assert_eq!(
+            normalized(&overlap.level),
+            normalized(&level(&[
+                [(0.0, 0.0), (2.0, 0.0)],
+                [(2.0, 0.0), (4.0, 0.0)],
+                [(4.0, 0.0), (6.0, 0.0)],
+            ])),
+        );
+
+        let contained = plan(
+            &level(&[[(0.0, 0.0), (6.0, 0.0)], [(2.0, 0.0), (4.0, 0.0)]]),
+            &options(false, true, false),
+        );
+        assert_eq!(contained.removed.len(), 1);
+        assert_eq!(contained.level.surfaces.len(), 3);
+    }
+
+    #[test]
+    fn near_endpoints_merge() {
+        let plan = plan(
+            &level(&[
+                [(0.0, 0.0), (1.0, 0.0)],
+                [(1.01, 0.01), (1.0, 1.0)],
+                [(1.0, 1.0), (1.02, 1.0)],
+            ]),
+            &options(true, false, false),
+        );
+        assert_eq!(plan.merged.len(), 2);
+        assert_eq!(plan.removed.len(), 1);
+        assert_eq!(
+            normalized(&plan.level),
+            normalized(&level(&[
+                [(0.0, 0.0), (1.0, 0.0)],
+                [(1.0, 0.0), (1.0, 1.0)]
+            ])),
+        );
+    }
+
+    #[test]
+    fn dangling_chain_removed() {
+        let triangle = [
+            [(0.0, 0.0), (2.0, 0.0)],
+            [(2.0, 0.0), (1.0, 2.0)],
+            [(1.0, 2.0), (0.0, 0.0)],
+        ];
+        let chain = [[(2.0, 0.0), (3.0, 0.0)], [(3.0, 0.0), (4.0, 1.0)]];
+        let plan = plan(
+            &level(&[triangle.as_slice(), chain.as_slice()].concat()),
+            &options(false, false, true),
+        );
+        assert_eq!(plan.removed.len(), 2);
+        assert_eq!(normalized(&plan.level), normalized(&level(&triangle)));
+    }
+}
@@ -0,0 +1,115 @@
+use super::*;
+
+/// Part of a level, positions are relative to the center of what was copied
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Fragment {
+    pub surfaces: Vec<Surface>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start_pos: Option<vec2<f32>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub finish_pos: Option<vec2<f32>>,
+}
+
+impl Fragment {
+    pub fn copy(level: &Level, selection: &selection::Selection) -> Option<Self> {
+        let start_pos = selection.start.then_some(level.start_pos);
+        let finish_pos = selection.finish.then_some(level.finish_pos);
+        let surfaces: Vec<Surface> = selection
+            .surfaces
+            .iter()
+            .map(|&index| level.surfaces[index].clone())
+            .collect();
+        let mut points = surfaces
+            .iter()
+            .flat_map(|surface| surface.ends)
+            .chain(start_pos)
+            .chain(finish_pos);
+        let first = points.next()?;
+        let (min, max) = points.fold((first, first), |(min, max), p| {
+            (
+                vec2(min.x.min(p.x), min.y.min(p.y)),
+                vec2(max.x.max(p.x), max.y.max(p.y)),
+            )
+        });
+        let origin = (min + max) / 2.0;
+        Some(Self {
+            surfaces: surfaces
+                .into_iter()
+                .map(|surface| Surface {
+                    ends: surface.ends.map(|end| end - origin),
+                })
+                .collect(),
+            start_pos: start_pos.map(|pos| pos - origin),
+            finish_pos: finish_pos.map(|pos| pos - origin),
+        })
+    }
+
+    pub fn map(&self, f: impl Fn(vec2<f32>) -> vec2<f32>) -> Self {
+        Self {
+            surfaces: self
+                .surfaces
+                .iter()
+                .map(|surface| Surface {
+                    ends: surface.ends.map(&f),
+                })
+                .collect(),
+            start_pos: self.start_pos.map(&f),
+            finish_pos: self.finish_pos.map(&f),
+        }
+    }
+
+    /// Single edit adding the fragment centered at `pos`, markers are moved
+    pub fn paste(&self, level: &Level, pos: vec2<f32>) -> history::Command {
+        let mut commands = vec![history::Command::AddSurfaces(
+            self.surfaces
+                .iter()
+                .map(|surface| Surface {
+                    ends: surface.ends.map(|end| end + pos),
+                })
+                .collect(),
+        )];
+        if let Some(start_pos) = self.start_pos {
+            commands.push(history::Command::SetStart {
+                before: level.start_pos,
+                after: start_pos + pos,
+            });
+        }
+        if let Some(finish_pos) = self.finish_pos {
+            commands.push(history::Command::SetFinish {
+                before: level.finish_pos,
+                after: finish_pos + pos,
+            });
+        }
+        history::Command::Batch(commands)
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+thread_local! {
+    /// No system clipboard access on the web, copies only work within the page
+    static CLIPBOARD: RefCell<String> = RefCell::new(String::new());
+}
+
+pub fn set_text(text: String) -> anyhow::Result<()> {
+    #[cfg(target_arch = "wasm32")]
+    {
+        CLIPBOARD.with(|clipboard| *clipboard.borrow_mut() = text);
+        Ok(())
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        arboard::Clipboard::new()?.set_text(text)?;
+        Ok(())
+    }
+}
+
+pub fn get_text() -> anyhow::Result<String> {
+    #[cfg(target_arch = "wasm32")]
+    {
+        Ok(CLIPBOARD.with(|clipboard| clipboard.borrow().clone()))
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        Ok(arboard::Clipboard::new()?.get_text()?)
+    }
+}
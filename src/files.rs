@@ -0,0 +1,46 @@
+/// Root of everything the game writes outside of the shipped assets
+pub fn data_dir() -> Option<std::path::PathBuf> {
+    #[cfg(target_arch = "wasm32")]
+    {
+        None
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        dirs::data_dir().map(|dir| dir.join(env!("CARGO_PKG_NAME")))
+    }
+}
+
+/// Writes into a temporary file that replaces the target, so it is never left half written.
+/// The temporary file is named after the whole file name, so targets differing
+/// only in extension don't share it
+pub fn write_atomic(path: &std::path::Path, contents: &[u8]) -> anyhow::Result<()> {
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| anyhow::anyhow!("{path:?} is not a file path"))?;
+    let mut temp_name = file_name.to_owned();
+    temp_name.push(".tmp");
+    let temp = path.with_file_name(temp_name);
+    std::fs::write(&temp, contents)?;
+    std::fs::rename(&temp, path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn write_atomic_replaces_the_file() {
+        let dir = std::env::temp_dir().join(format!("write_atomic_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let json = dir.join("level.json");
+        let toml = dir.join("level.toml");
+        write_atomic(&json, b"old").unwrap();
+        write_atomic(&json, b"new").unwrap();
+        write_atomic(&toml, b"other").unwrap();
+        assert_eq!(std::fs::read(&json).unwrap(), b"new");
+        assert_eq!(std::fs::read(&toml).unwrap(), b"other");
+        assert!(!dir.join("level.json.tmp").exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
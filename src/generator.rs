@@ -0,0 +1,254 @@
+use super::*;
+
+pub struct Options {
+    pub seed: u64,
+    /// From 0 to 1, scales ledge heights, shaft lengths and wall roughness
+    pub difficulty: f32,
+    pub sections: usize,
+    /// How many seeds to try before giving up on finding a solvable level
+    pub attempts: usize,
+    /// Solver budget per attempt, in expanded search nodes
+    pub max_nodes: usize,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            seed: 0,
+            difficulty: 0.5,
+            sections: 6,
+            attempts: 20,
+            max_nodes: 3000,
+        }
+    }
+}
+
+/// Cave is a corridor between a floor and a ceiling polyline going right and up
+struct Cave {
+    floor: Vec<vec2<f32>>,
+    ceiling: Vec<vec2<f32>>,
+}
+
+impl Cave {
+    fn floor_end(&self) -> vec2<f32> {
+        *self.floor.last().unwrap()
+    }
+
+    fn ceiling_end(&self) -> vec2<f32> {
+        *self.ceiling.last().unwrap()
+    }
+
+    fn corridor(&mut self, rng: &mut impl Rng, length: f32, roughness: f32) {
+        let steps = (length / 0.5).ceil().max(1.0) as usize;
+        let floor = self.floor_end();
+        let ceiling = self.ceiling_end();
+        for i in 1..=steps {
+            let x = length * i as f32 / steps as f32;
+            let mut bump = || {
+                if i == steps {
+                    0.0
+                } else {
+                    rng.gen_range(-1.0..=1.0) * roughness
+                }
+            };
+            self.floor.push(floor + vec2(x, bump()));
+            self.ceiling.push(ceiling + vec2(x, bump()));
+        }
+    }
+
+    fn ledge(&mut self, height: f32) {
+        let floor = self.floor_end();
+        let ceiling = self.ceiling_end();
+        self.floor.push(floor + vec2(0.0, height));
+        self.ceiling.push(ceiling + vec2(0.0, height));
+    }
+
+    fn shaft(&mut self, width: f32, height: f32) {
+        let floor = self.floor_end();
+        let ceiling = self.ceiling_end();
+        self.floor.push(floor + vec2(width, 0.0));
+        self.floor.push(floor + vec2(width, height));
+        self.ceiling.push(ceiling + vec2(0.0, height));
+        self.ceiling.push(ceiling + vec2(width, height));
+    }
+
+    fn into_level(self) -> Level {
+        let mut points = self.floor;
+        points.extend(self.ceiling.into_iter().rev());
+        let surfaces = (0..points.len())
+            .map(|i| Surface {
+                ends: [points[i], points[(i + 1) % points.len()]],
+            })
+            .filter(|surface| surface.ends[0] != surface.ends[1])
+            .collect();
+        Level {
+            start_pos: vec2::ZERO,
+            finish_pos: vec2::ZERO,
+            surfaces,
+            finish_radius: None,
+        }
+    }
+}
+
+pub fn generate_unchecked(
+    config: &Config,
+    seed: u64,
+    difficulty: f32,
+    sections: usize,
+) -> anyhow::Result<Level> {
+    let player = &config.player;
+    if !(0.0..=1.0).contains(&difficulty) {
+        anyhow::bail!("difficulty {difficulty} is not between 0 and 1");
+    }
+    // Wide enough to fit when small, narrow enough to wedge in when grown
+    let shaft_widths = player.min_radius * 3.0..player.max_radius * 1.8;
+    if shaft_widths.is_empty() {
+        anyhow::bail!(
+            "player radius range {}..{} is too narrow for shafts",
+            player.min_radius,
+            player.max_radius,
+        );
+    }
+    let mut rng = StdRng::seed_from_u64(seed);
+    let height = player.max_radius * 2.0 * rng.gen_range(1.3..2.0);
+    let mut cave = Cave {
+        floor: vec![vec2::ZERO],
+        ceiling: vec![vec2(0.0, height)],
+    };
+    let roughness = difficulty * player.min_radius;
+    cave.corridor(&mut rng, 2.0, roughness);
+    for _ in 0..sections {
+        match rng.gen_range(0..3) {
+            0 => {
+                let length = rng.gen_range(1.0..3.0);
+                cave.corridor(&mut rng, length, roughness);
+            }
+            1 => {
+                let max = player.max_radius * (0.5 + 1.5 * difficulty);
+                let step = rng.gen_range(player.min_radius..=max.max(player.min_radius));
+                cave.ledge(step);
+                cave.corridor(&mut rng, 1.0, roughness);
+            }
+            _ => {
+                let width = rng.gen_range(shaft_widths.clone());
+                let depth = rng.gen_range(1.0..=1.0 + 4.0 * difficulty);
+                cave.shaft(width, depth);
+                cave.corridor(&mut rng, 1.0, roughness);
+            }
+        }
+    }
+    cave.corridor(&mut rng, 2.0, 0.0);
+    let end = cave.floor_end();
+    let mut level = cave.into_level();
+    // The insides fill needs the origin in open space, so the level is moved to start there
+    let start = vec2(1.0, height / 2.0);
+    for surface in &mut level.surfaces {
+        surface.ends = surface.ends.map(|end| end - start);
+    }
+    level.start_pos = vec2::ZERO;
+    level.finish_pos = end + vec2(-1.0, player.max_radius) - start;
+    Ok(level)
+}
+
+/// Greedy best-first search over held inputs, returns whether the finish is reachable
+pub fn is_solvable(config: &Config, level: &Level, max_nodes: usize) -> bool {
+    const HOLD_TICKS: u64 = physics::TICKS_PER_SECOND / 5;
+    const CELL: f32 = 0.1;
+    let inputs: Vec<(physics::Scaling, vec2<f32>)> = [
+        physics::Scaling::Grow,
+        physics::Scaling::Shrink,
+        physics::Scaling::None,
+    ]
+    .into_iter()
+    .flat_map(|scaling| {
+        (0..8).map(move |i| {
+            let direction = vec2(1.0, 0.0).rotate(Angle::from_degrees(i as f32 * 45.0));
+            (scaling, direction)
+        })
+    })
+    .collect();
+
+    let key = |player: &Player| {
+        (
+            (player.pos / CELL).map(|x| x.floor() as i64),
+            (player.radius / CELL).floor() as i64,
+            (player.vel / (CELL * 10.0)).map(|x| x.floor() as i64),
+        )
+    };
+    let mut visited = HashSet::new();
+    let mut queue = std::collections::BinaryHeap::new();
+    let start = Player::new(config, level.start_pos);
+    visited.insert(key(&start));
+    let priority = |player: &Player| -r32((player.pos - level.finish_pos).len());
+    let mut states = vec![start];
+    queue.push((priority(&states[0]), 0));
+    let mut expanded = 0;
+    while let Some((_, index)) = queue.pop() {
+        expanded += 1;
+        if expanded > max_nodes {
+            return false;
+        }
+        for &(scaling, direction) in &inputs {
+            let mut player = states[index].clone();
+            let input = physics::Input {
+                scaling,
+                cursor: player.pos + direction * player.radius,
+            };
+            if player.simulate(config, level, &input, HOLD_TICKS) {
+                return true;
+            }
+            if visited.insert(key(&player)) {
+                queue.push((priority(&player), states.len()));
+                states.push(player);
+            }
+        }
+    }
+    false
+}
+
+/// Tries consecutive seeds until the level is valid and solvable, returns the seed used
+pub fn generate(config: &Config, options: &Options) -> anyhow::Result<(u64, Level)> {
+    for attempt in 0..options.attempts as u64 {
+        let seed = options.seed.wrapping_add(attempt);
+        let level = generate_unchecked(config, seed, options.difficulty, options.sections)?;
+        if validate::validate(config, &level).is_empty()
+            && is_solvable(config, &level, options.max_nodes)
+        {
+            return Ok((seed, level));
+        }
+    }
+    anyhow::bail!("no solvable level found in {} attempts", options.attempts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> Config {
+        toml::from_str(include_str!("../assets/config.toml")).unwrap()
+    }
+
+    #[test]
+    fn fixed_seed() {
+        let config = config();
+        let options = Options {
+            seed: 7,
+            sections: 3,
+            ..default()
+        };
+        let (seed, level) = generate(&config, &options).unwrap();
+        let (same_seed, same_level) = generate(&config, &options).unwrap();
+        assert_eq!(seed, same_seed);
+        assert!(level == same_level);
+        assert!(validate::validate(&config, &level).is_empty());
+        assert!(is_solvable(&config, &level, options.max_nodes));
+    }
+
+    #[test]
+    fn invalid_options_rejected() {
+        let mut config = config();
+        assert!(generate_unchecked(&config, 0, 1.5, 6).is_err());
+        config.player.min_radius = config.player.max_radius;
+        assert!(generate_unchecked(&config, 0, 0.5, 6).is_err());
+    }
+}
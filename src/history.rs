@@ -0,0 +1,282 @@
+use super::*;
+
+/// Reversible level edit
+#[derive(Clone)]
+pub enum Command {
+    /// Appended to the end of the surface list
+    AddSurfaces(Vec<Surface>),
+    /// Removed surfaces with the indices they had, in increasing order
+    RemoveSurfaces(Vec<(usize, Surface)>),
+    /// Surfaces changed in place as (index, before, after)
+    ModifySurfaces(Vec<(usize, Surface, Surface)>),
+    SetStart {
+        before: vec2<f32>,
+        after: vec2<f32>,
+    },
+    SetFinish {
+        before: vec2<f32>,
+        after: vec2<f32>,
+    },
+    SetFinishRadius {
+        before: Option<f32>,
+        after: Option<f32>,
+    },
+    /// Whole level replaced, for imports
+    Replace {
+        before: Box<Level>,
+        after: Box<Level>,
+    },
+    /// Applied in order, reverted in reverse order
+    Batch(Vec<Command>),
+}
+
+impl Command {
+    pub fn remove_surfaces(level: &Level, mut indices: Vec<usize>) -> Self {
+        indices.sort_unstable();
+        indices.dedup();
+        Self::RemoveSurfaces(
+            indices
+                .into_iter()
+                .map(|index| (index, level.surfaces[index].clone()))
+                .collect(),
+        )
+    }
+
+    pub fn replace(level: &Level, after: Level) -> Self {
+        Self::Replace {
+            before: Box::new(level.clone()),
+            after: Box::new(after),
+        }
+    }
+
+    pub fn apply(&self, level: &mut Level) {
+        match self {
+            Self::AddSurfaces(surfaces) => level.surfaces.extend(surfaces.iter().cloned()),
+            Self::RemoveSurfaces(removed) => {
+                for (index, _) in removed.iter().rev() {
+                    level.surfaces.remove(*index);
+                }
+            }
+            Self::ModifySurfaces(changes) => {
+                for (index, _, after) in changes {
+                    level.surfaces[*index] = after.clone();
+                }
+            }
+            Self::SetStart { after, .. } => level.start_pos = *after,
+            Self::SetFinish { after, .. } => level.finish_pos = *after,
+            Self::SetFinishRadius { after, .. } => level.finish_radius = *after,
+            Self::Replace { after, .. } => *level = (**after).clone(),
+            Self::Batch(commands) => {
+                for command in commands {
+                    command.apply(level);
+                }
+            }
+        }
+    }
+
+    pub fn revert(&self, level: &mut Level) {
+        match self {
+            Self::AddSurfaces(surfaces) => {
+                level
+                    .surfaces
+                    .truncate(level.surfaces.len() - surfaces.len());
+            }
+            Self::RemoveSurfaces(removed) => {
+                for (index, surface) in removed {
+                    level.surfaces.insert(*index, surface.clone());
+                }
+            }
+            Self::ModifySurfaces(changes) => {
+                for (index, before, _) in changes.iter().rev() {
+                    level.surfaces[*index] = before.clone();
+                }
+            }
+            Self::SetStart { before, .. } => level.start_pos = *before,
+            Self::SetFinish { before, .. } => level.finish_pos = *before,
+            Self::SetFinishRadius { before, .. } => level.finish_radius = *before,
+            Self::Replace { before, .. } => *level = (**before).clone(),
+            Self::Batch(commands) => {
+                for command in commands.iter().rev() {
+                    command.revert(level);
+                }
+            }
+        }
+    }
+}
+
+/// Undo and redo stacks of a single level
+#[derive(Default)]
+pub struct History {
+    undo: Vec<Command>,
+    redo: Vec<Command>,
+}
+
+impl History {
+    pub fn push(&mut self, command: Command) {
+        self.undo.push(command);
+        self.redo.clear();
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    /// Returns whether there was anything to undo
+    pub fn undo(&mut self, level: &mut Level) -> bool {
+        let Some(command) = self.undo.pop() else {
+            return false;
+        };
+        command.revert(level);
+        self.redo.push(command);
+        true
+    }
+
+    /// Returns whether there was anything to redo
+    pub fn redo(&mut self, level: &mut Level) -> bool {
+        let Some(command) = self.redo.pop() else {
+            return false;
+        };
+        command.apply(level);
+        self.undo.push(command);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn surface(a: (f32, f32), b: (f32, f32)) -> Surface {
+        Surface {
+            ends: [vec2(a.0, a.1), vec2(b.0, b.1)],
+        }
+    }
+
+    fn level() -> Level {
+        Level {
+            start_pos: vec2(0.0, 1.0),
+            finish_pos: vec2(5.0, 1.0),
+            surfaces: vec![
+                surface((0.0, 0.0), (1.0, 0.0)),
+                surface((1.0, 0.0), (2.0, 0.0)),
+                surface((2.0, 0.0), (3.0, 0.0)),
+                surface((3.0, 0.0), (4.0, 0.0)),
+            ],
+            finish_radius: None,
+        }
+    }
+
+    /// Applies and reverts the command, checking the level in between
+    fn assert_reversible(command: Command, applied: impl Fn(&Level) -> bool) {
+        let original = level();
+        let mut level = original.clone();
+        command.apply(&mut level);
+        assert!(applied(&level), "command was not applied");
+        assert!(level != original, "command changed nothing");
+        command.revert(&mut level);
+        assert!(level == original, "revert did not restore the level");
+    }
+
+    #[test]
+    fn add_surfaces() {
+        assert_reversible(
+            Command::AddSurfaces(vec![surface((4.0, 0.0), (4.0, 1.0))]),
+            |level| level.surfaces.len() == 5,
+        );
+    }
+
+    #[test]
+    fn remove_surfaces() {
+        let command = Command::remove_surfaces(&level(), vec![3, 1, 1]);
+        assert_reversible(command, |level| {
+            level.surfaces.len() == 2
+                && level.surfaces[0] == surface((0.0, 0.0), (1.0, 0.0))
+                && level.surfaces[1] == surface((2.0, 0.0), (3.0, 0.0))
+        });
+    }
+
+    #[test]
+    fn modify_surfaces() {
+        let level = level();
+        let moved = surface((0.0, 0.0), (1.0, 0.5));
+        let command = Command::ModifySurfaces(vec![
+            (0, level.surfaces[0].clone(), moved.clone()),
+            (2, level.surfaces[2].clone(), moved.clone()),
+        ]);
+        assert_reversible(command, |level| {
+            level.surfaces[0] == moved && level.surfaces[2] == moved
+        });
+    }
+
+    #[test]
+    fn set_start_finish_and_radius() {
+        assert_reversible(
+            Command::SetStart {
+                before: vec2(0.0, 1.0),
+                after: vec2(1.0, 1.0),
+            },
+            |level| level.start_pos == vec2(1.0, 1.0),
+        );
+        assert_reversible(
+            Command::SetFinish {
+                before: vec2(5.0, 1.0),
+                after: vec2(6.0, 1.0),
+            },
+            |level| level.finish_pos == vec2(6.0, 1.0),
+        );
+        assert_reversible(
+            Command::SetFinishRadius {
+                before: None,
+                after: Some(0.5),
+            },
+            |level| level.finish_radius == Some(0.5),
+        );
+    }
+
+    #[test]
+    fn replace() {
+        let other = Level {
+            surfaces: Vec::new(),
+            ..level()
+        };
+        assert_reversible(Command::replace(&level(), other), |level| {
+            level.surfaces.is_empty()
+        });
+    }
+
+    #[test]
+    fn batch_order() {
+        // The second command refers to indices after the first one was applied
+        let level = level();
+        let added = surface((4.0, 0.0), (4.0, 1.0));
+        let command = Command::Batch(vec![
+            Command::RemoveSurfaces(vec![(0, level.surfaces[0].clone())]),
+            Command::AddSurfaces(vec![added.clone()]),
+            Command::ModifySurfaces(vec![(3, added.clone(), surface((4.0, 0.0), (5.0, 1.0)))]),
+        ]);
+        assert_reversible(command, |level| {
+            level.surfaces.len() == 4 && level.surfaces[3] == surface((4.0, 0.0), (5.0, 1.0))
+        });
+    }
+
+    #[test]
+    fn undo_redo() {
+        let original = level();
+        let mut level = original.clone();
+        let mut history = History::default();
+        let command = Command::AddSurfaces(vec![surface((4.0, 0.0), (4.0, 1.0))]);
+        command.apply(&mut level);
+        history.push(command);
+        let edited = level.clone();
+        assert!(history.undo(&mut level));
+        assert!(level == original);
+        assert!(!history.undo(&mut level));
+        assert!(history.redo(&mut level));
+        assert!(level == edited);
+        assert!(!history.can_redo());
+    }
+}
@@ -0,0 +1,39 @@
+use super::*;
+
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+/// Polls modification times since there are only a handful of files to watch
+#[derive(Default)]
+pub struct Watcher {
+    mtimes: HashMap<PathBuf, SystemTime>,
+    initialized: bool,
+}
+
+impl Watcher {
+    /// Files modified or created since the previous poll
+    pub fn poll(&mut self, paths: impl IntoIterator<Item = PathBuf>) -> Vec<PathBuf> {
+        let mut changed = Vec::new();
+        for path in paths {
+            let Ok(mtime) = std::fs::metadata(&path).and_then(|metadata| metadata.modified())
+            else {
+                continue;
+            };
+            if self.mtimes.insert(path.clone(), mtime) != Some(mtime) && self.initialized {
+                changed.push(path);
+            }
+        }
+        self.initialized = true;
+        changed
+    }
+}
+
+/// Json files of a level directory
+pub fn level_files(dir: &std::path::Path) -> impl Iterator<Item = PathBuf> {
+    std::fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().map_or(false, |ext| ext == "json"))
+}
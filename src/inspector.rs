@@ -0,0 +1,163 @@
+use super::*;
+
+/// Position that can be edited numerically
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Target {
+    /// Surface index and end index
+    End(usize, usize),
+    Start,
+    Finish,
+}
+
+impl Target {
+    pub fn get(self, level: &Level) -> vec2<f32> {
+        match self {
+            Self::End(index, end) => level.surfaces[index].ends[end],
+            Self::Start => level.start_pos,
+            Self::Finish => level.finish_pos,
+        }
+    }
+
+    pub fn set(self, level: &mut Level, pos: vec2<f32>) {
+        match self {
+            Self::End(index, end) => level.surfaces[index].ends[end] = pos,
+            Self::Start => level.start_pos = pos,
+            Self::Finish => level.finish_pos = pos,
+        }
+    }
+
+    fn name(self) -> String {
+        match self {
+            Self::End(index, end) => format!("surface {index} end {end}"),
+            Self::Start => "start".to_owned(),
+            Self::Finish => "finish".to_owned(),
+        }
+    }
+}
+
+pub fn targets(selection: &selection::Selection) -> Vec<Target> {
+    selection
+        .surfaces
+        .iter()
+        .flat_map(|&index| [Target::End(index, 0), Target::End(index, 1)])
+        .chain(selection.start.then_some(Target::Start))
+        .chain(selection.finish.then_some(Target::Finish))
+        .collect()
+}
+
+/// Inspected values as they were before an edit
+pub struct Values {
+    positions: Vec<(Target, vec2<f32>)>,
+    finish_radius: Option<f32>,
+}
+
+impl Values {
+    pub fn new(level: &Level, targets: &[Target]) -> Self {
+        Self {
+            positions: targets
+                .iter()
+                .map(|&target| (target, target.get(level)))
+                .collect(),
+            finish_radius: level.finish_radius,
+        }
+    }
+}
+
+/// Edits the values in place, returns whether anything changed
+/// and whether a value is still being dragged or typed
+pub fn ui(
+    ui: &mut egui::Ui,
+    config: &Config,
+    level: &mut Level,
+    targets: &[Target],
+) -> (bool, bool) {
+    let mut changed = false;
+    let mut active = false;
+    egui::Grid::new("inspector").show(ui, |ui| {
+        for &target in targets {
+            ui.label(target.name());
+            let mut pos = target.get(level);
+            for (value, prefix) in [(&mut pos.x, "x "), (&mut pos.y, "y ")] {
+                let response = ui.add(egui::DragValue::new(value).speed(0.01).prefix(prefix));
+                changed |= response.changed();
+                active |= response.dragged() || response.has_focus();
+            }
+            if pos != target.get(level) {
+                target.set(level, pos);
+            }
+            ui.end_row();
+        }
+        if targets.contains(&Target::Finish) {
+            ui.label("finish radius");
+            let mut radius = level.finish_radius(config);
+            let response = ui.add(
+                egui::DragValue::new(&mut radius)
+                    .speed(0.01)
+                    .clamp_range(0.05..=5.0),
+            );
+            if response.changed() {
+                level.finish_radius = Some(radius);
+                changed = true;
+            }
+            active |= response.dragged() || response.has_focus();
+            if ui
+                .add_enabled(level.finish_radius.is_some(), egui::Button::new("default"))
+                .clicked()
+            {
+                level.finish_radius = None;
+                changed = true;
+            }
+            ui.end_row();
+        }
+    });
+    (changed, active)
+}
+
+/// Puts the original values back and returns the edit from them to the current ones
+pub fn command(level: &mut Level, original: &Values) -> history::Command {
+    let after = level.clone();
+    for &(target, pos) in &original.positions {
+        target.set(level, pos);
+    }
+    level.finish_radius = original.finish_radius;
+    let mut surfaces: Vec<usize> = original
+        .positions
+        .iter()
+        .filter_map(|&(target, _)| match target {
+            Target::End(index, _) => Some(index),
+            _ => None,
+        })
+        .collect();
+    surfaces.dedup();
+    let mut commands = vec![history::Command::ModifySurfaces(
+        surfaces
+            .into_iter()
+            .map(|index| {
+                (
+                    index,
+                    level.surfaces[index].clone(),
+                    after.surfaces[index].clone(),
+                )
+            })
+            .collect(),
+    )];
+    if level.start_pos != after.start_pos {
+        commands.push(history::Command::SetStart {
+            before: level.start_pos,
+            after: after.start_pos,
+        });
+    }
+    if level.finish_pos != after.finish_pos {
+        commands.push(history::Command::SetFinish {
+            before: level.finish_pos,
+            after: after.finish_pos,
+        });
+    }
+    if level.finish_radius != after.finish_radius {
+        commands.push(history::Command::SetFinishRadius {
+            before: level.finish_radius,
+            after: after.finish_radius,
+        });
+    }
+    history::Command::Batch(commands)
+}
@@ -1,7 +1,33 @@
 #![allow(clippy::assigning_clones)]
+use anyhow::Context as _;
 use geng::prelude::*;
 use geng_egui::{egui, EguiGeng};
 
+mod campaign;
+mod cleanup;
+mod clipboard;
+mod files;
+mod generator;
+mod history;
+#[cfg(not(target_arch = "wasm32"))]
+mod hot_reload;
+mod inspector;
+mod migrations;
+mod pack;
+mod physics;
+mod prefab;
+mod replay;
+mod selection;
+mod share_code;
+mod snap;
+mod svg;
+mod tools;
+mod trajectory;
+mod tuning;
+mod validate;
+mod vertex;
+mod view;
+
 #[derive(geng::asset::Load)]
 struct Shaders {
     invert: ugli::Program,
@@ -12,6 +38,7 @@ struct Shaders {
     finish: ugli::Program,
     player: ugli::Program,
     selection: ugli::Program,
+    grid: ugli::Program,
 }
 
 #[derive(geng::asset::Load)]
@@ -35,17 +62,17 @@ pub struct Assets {
     shaders: Shaders,
 }
 
-#[derive(Deserialize)]
+#[derive(Serialize, Deserialize, Clone)]
 struct LevelMeshConfig {
     max_distance: f32,
 }
 
-#[derive(Deserialize)]
+#[derive(Serialize, Deserialize, Clone)]
 pub struct CursorConfig {
     hotspot: vec2<u16>,
 }
 
-#[derive(Deserialize)]
+#[derive(Serialize, Deserialize, Clone)]
 pub struct PlayerConfig {
     radius: f32,
     min_radius: f32,
@@ -53,26 +80,33 @@ pub struct PlayerConfig {
     scaling_speed: f32,
 }
 
-#[derive(Deserialize)]
+#[derive(Serialize, Deserialize, Clone)]
 struct StaticConfig {
     max_vel: f32,
     time_to_full: f32,
 }
 
-#[derive(Deserialize)]
+#[derive(Serialize, Deserialize, Clone)]
 struct EditorConfig {
     snap_distance: f32,
     cursor_rotation_speed: f32,
     camera_speed: f32,
+    grid_spacing: f32,
+    /// In degrees
+    angle_step: f32,
+    /// Seconds between saves of unsaved edits, 0 to only save with Ctrl+S
+    autosave_interval: f32,
+    /// Previous versions kept per level
+    backups: usize,
 }
 
-#[derive(Deserialize)]
+#[derive(Serialize, Deserialize, Clone)]
 struct CameraConfig {
     fov: f32,
     speed: f32,
 }
 
-#[derive(Deserialize)]
+#[derive(Serialize, Deserialize, Clone)]
 pub struct SfxConfig {
     level_volume: f32,
     win_volume: f32,
@@ -83,7 +117,7 @@ pub struct SfxConfig {
     hit_max_volume_speed: f32,
 }
 
-#[derive(Deserialize)]
+#[derive(Serialize, Deserialize, Clone)]
 pub struct Config {
     sfx: SfxConfig,
     finish_radius: f32,
@@ -104,7 +138,7 @@ struct Vertex {
     a_pos: vec2<f32>,
 }
 
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
 struct Surface {
     ends: [vec2<f32>; 2],
 }
@@ -146,55 +180,48 @@ impl Surface {
     }
 }
 
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
 struct Level {
-    #[serde(default = "default_start")]
     start_pos: vec2<f32>,
-    #[serde(default = "default_finish")]
     finish_pos: vec2<f32>,
-    #[serde(default)]
     surfaces: Vec<Surface>,
+    /// Overrides `Config::finish_radius`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    finish_radius: Option<f32>,
 }
 
-fn default_finish() -> vec2<f32> {
-    vec2(5.0, 0.0)
-}
-
-fn default_start() -> vec2<f32> {
-    vec2::ZERO
+impl Level {
+    fn finish_radius(&self, config: &Config) -> f32 {
+        self.finish_radius.unwrap_or(config.finish_radius)
+    }
 }
 
 struct Levels {
-    list: Vec<String>,
+    manifest: campaign::Manifest,
     map: HashMap<String, Level>,
 }
 
-impl geng::asset::Load for Levels {
-    type Options = ();
-    fn load(
-        _manager: &geng::asset::Manager,
-        path: &std::path::Path,
-        _options: &Self::Options,
-    ) -> geng::asset::Future<Self> {
-        let path = path.to_owned();
-        async move {
-            let path = &path;
-            let list: Vec<String> = file::load_json(path.join("_list.json")).await.unwrap();
-            let levels = future::join_all(list.into_iter().map(|level_name| async move {
-                let level: Level = file::load_json(path.join(&level_name).with_extension("json"))
-                    .await
-                    .unwrap();
-                (level_name, level)
-            }))
-            .await;
-            Ok(Self {
-                list: levels.iter().map(|(name, _level)| name.clone()).collect(),
-                map: levels.into_iter().collect(),
-            })
-        }
-        .boxed_local()
+impl Levels {
+    async fn load_dir(path: &std::path::Path) -> anyhow::Result<Self> {
+        let manifest = campaign::Manifest::load(path).await?;
+        let names: HashSet<String> = manifest
+            .campaigns
+            .iter()
+            .flat_map(|campaign| &campaign.levels)
+            .map(|level| level.name.clone())
+            .collect();
+        let levels = future::join_all(names.into_iter().map(|level_name| async move {
+            let json = file::load_json(path.join(&level_name).with_extension("json")).await?;
+            let level = migrations::upgrade(json)
+                .map_err(|e| anyhow::anyhow!("failed to load level {level_name:?}: {e}"))?;
+            anyhow::Ok((level_name, level))
+        }))
+        .await;
+        Ok(Self {
+            manifest,
+            map: levels.into_iter().collect::<anyhow::Result<_>>()?,
+        })
     }
-    const DEFAULT_EXT: Option<&'static str> = None;
 }
 
 struct LevelMesh {
@@ -263,6 +290,7 @@ impl LevelMesh {
     }
 }
 
+#[derive(Clone)]
 struct Player {
     pos: vec2<f32>,
     vel: vec2<f32>,
@@ -271,17 +299,60 @@ struct Player {
     scale_origin: vec2<f32>,
 }
 
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Tool {
+    Draw,
+    Select,
+    Vertex,
+    Polyline,
+    Trajectory,
+}
+
 pub struct Game {
     framebuffer_size: vec2<f32>,
     geng: Geng,
     assets: Assets,
     config: Config,
+    /// Config as it is in config.toml, for reverting live tuning
+    saved_config: Config,
     camera: Camera2d,
     quad: ugli::VertexBuffer<Vertex>,
     level: Level,
     level_mesh: LevelMesh,
     time: f32,
     start_draw: Option<vec2<f32>>,
+    tool: Tool,
+    selection: selection::Selection,
+    vertex_drag: Option<vertex::Drag>,
+    /// Inspector values before the edit in progress
+    inspector_original: Option<inspector::Values>,
+    /// Start or finish being dragged, with its values before the drag
+    handle_drag: Option<(inspector::Target, inspector::Values)>,
+    /// Vertices of the polyline being drawn
+    polyline: Vec<vec2<f32>>,
+    snap_modes: snap::Modes,
+    prefabs: Vec<prefab::Prefab>,
+    /// Prefab being placed, follows the cursor
+    placement: Option<prefab::Placement>,
+    /// Name for saving the selection as a prefab
+    prefab_name: String,
+    cleanup: cleanup::Options,
+    /// Recomputed when the level or options change while the preview is enabled
+    cleanup_plan: Option<cleanup::Plan>,
+    cleanup_preview: bool,
+    trajectory: trajectory::Preview,
+    trajectory_path: Option<trajectory::Path>,
+    /// Set when the level, start point or segments change, simulated again
+    /// the next frame the trajectory tool is active
+    trajectory_stale: bool,
+    /// Zoom of the editor camera, the play camera keeps `CameraConfig::fov`
+    editor_fov: f32,
+    /// World position grabbed with the middle mouse button
+    pan: Option<vec2<f32>>,
+    show_minimap: bool,
+    /// Name for creating, duplicating or renaming levels
+    level_name: String,
+    confirm_delete: bool,
     temp_texture: ugli::Texture,
     temp_renderbuffer: ugli::Renderbuffer<ugli::DepthStencilValue>,
     player: Option<Player>,
@@ -289,12 +360,60 @@ pub struct Game {
     editor_mode: bool,
     cli: CliArgs,
     unprocessed: f32,
-    levels: Levels,
+    packs: Vec<pack::Pack>,
+    current_pack: usize,
+    current_campaign: usize,
     current_level: usize,
+    /// Ticks since the current level started
+    level_ticks: u64,
+    /// Ticks since the run started, this is the official time
+    run_ticks: u64,
+    /// Completed levels of the current run, `None` once editing made the run unverifiable
+    replay: Option<replay::Replay>,
+    level_run: Option<replay::LevelRun>,
+    /// Undo history of every level edited this session, by pack index and level name
+    histories: HashMap<(usize, String), history::History>,
+    /// Current level has edits that are not saved to its file
+    dirty: bool,
+    /// Seconds since the level became dirty
+    unsaved_time: f32,
+    /// Pack, campaign and level to switch to once unsaved changes are saved or discarded
+    pending_switch: Option<(usize, usize, usize)>,
+    progress: campaign::Progress,
+    show_level_select: bool,
+    /// By path, tracks that failed to load are missing
+    music_tracks: HashMap<std::path::PathBuf, geng::Sound>,
+    music: geng::SoundEffect,
+    /// Track from the manifest that is playing, `None` for the default one
+    music_track: Option<std::path::PathBuf>,
     draw_insides: bool,
     finished: bool,
     scale_up_sfx: geng::SoundEffect,
     scale_down_sfx: geng::SoundEffect,
+    issues: Vec<validate::Issue>,
+    share_code: String,
+    svg_path: String,
+    generator_options: generator::Options,
+    confirm_generate: bool,
+    editor_error: Option<String>,
+    #[cfg(not(target_arch = "wasm32"))]
+    watcher: hot_reload::Watcher,
+    next_reload_poll: f32,
+    /// Last hot reload failures, the previous good state is kept
+    config_reload_error: Option<String>,
+    /// By pack index
+    level_reload_errors: std::collections::BTreeMap<usize, String>,
+}
+
+fn config_path() -> std::path::PathBuf {
+    run_dir().join("assets").join("config.toml")
+}
+
+fn format_time(time: f32) -> String {
+    let ms = (time * 1000.0) as i64;
+    let seconds = ms / 1000;
+    let minutes = seconds / 60;
+    format!("{}:{:02}:{:03}", minutes, seconds % 60, ms % 1000)
 }
 
 trait SoundExt {
@@ -316,13 +435,11 @@ impl Game {
             .load(run_dir().join("assets"))
             .await
             .unwrap();
-        let config: Config = file::load_detect(run_dir().join("assets").join("config.toml"))
-            .await
-            .unwrap();
+        let config: Config = file::load_detect(config_path()).await.unwrap();
         geng.audio()
             .master_volume()
             .set_value(config.sfx.master_volume);
-        assets.music.play().set_volume(config.sfx.music_volume);
+        let music = assets.music.play_with_volume(config.sfx.music_volume);
 
         geng.window().set_cursor_type(geng::CursorType::Custom {
             image: geng
@@ -332,15 +449,38 @@ impl Game {
                 .unwrap(),
             hotspot: config.cursor.hotspot,
         });
-        let levels: Levels = geng
-            .asset_manager()
-            .load(run_dir().join("assets").join("levels"))
-            .await
-            .unwrap();
-        let level = levels.map[&levels.list[0]].clone();
+        let mut packs = pack::load_all(&cli.levels).await;
+        if packs.is_empty() {
+            log::error!("No level pack could be loaded");
+            packs.push(pack::Pack::fallback());
+        }
+        let mut music_tracks: HashMap<std::path::PathBuf, geng::Sound> = HashMap::new();
+        for path in packs.iter().flat_map(|pack| {
+            pack.levels
+                .manifest
+                .music_tracks()
+                .map(|track| pack.dir.join(track))
+        }) {
+            if music_tracks.contains_key(&path) {
+                continue;
+            }
+            let mut options: <geng::Sound as geng::asset::Load>::Options = default();
+            options.looped = true;
+            match geng.asset_manager().load_with(&path, &options).await {
+                Ok(sound) => {
+                    music_tracks.insert(path, sound);
+                }
+                Err(e) => log::error!("Failed to load music {path:?}: {e}"),
+            }
+        }
+        let levels = &packs[0].levels;
+        let level = levels.map[&levels.manifest.campaigns[0].levels[0].name].clone();
         let level_mesh = LevelMesh::new(geng, &config, &level);
+        let replay = Some(replay::Replay::new(&packs[0].name));
         let mut result = Self {
-            levels,
+            replay,
+            packs,
+            current_pack: 0,
             framebuffer_size: vec2::splat(1.0),
             level,
             level_mesh,
@@ -361,25 +501,65 @@ impl Game {
                     .collect(),
             ),
             start_draw: None,
+            tool: Tool::Draw,
+            selection: default(),
+            vertex_drag: None,
+            inspector_original: None,
+            handle_drag: None,
+            polyline: Vec::new(),
+            snap_modes: default(),
+            prefabs: prefab::load_all(),
+            placement: None,
+            prefab_name: String::new(),
+            cleanup: default(),
+            cleanup_plan: None,
+            cleanup_preview: false,
+            trajectory: default(),
+            trajectory_path: None,
+            trajectory_stale: true,
+            editor_fov: config.camera.fov,
+            pan: None,
+            show_minimap: false,
+            level_name: String::new(),
+            confirm_delete: false,
             temp_texture: ugli::Texture2d::new_with(geng.ugli(), vec2::splat(1), |_| Rgba::WHITE),
             temp_renderbuffer: ugli::Renderbuffer::new(geng.ugli(), vec2::splat(1)),
-            player: Some(Player {
-                pos: vec2::ZERO,
-                vel: vec2::ZERO,
-                radius: config.player.radius,
-                r#static: 0.0,
-                scale_origin: vec2::ZERO,
-            }),
+            player: Some(Player::new(&config, vec2::ZERO)),
             editor_mode: false,
             egui: Rc::new(RefCell::new(EguiGeng::new(geng))),
             cli,
             unprocessed: 0.0,
+            current_campaign: 0,
             current_level: 0,
+            level_ticks: 0,
+            run_ticks: 0,
+            level_run: None,
+            histories: HashMap::new(),
+            dirty: false,
+            unsaved_time: 0.0,
+            pending_switch: None,
+            progress: campaign::Progress::load(),
+            show_level_select: false,
+            music_tracks,
+            music,
+            music_track: None,
             draw_insides: true,
             finished: false,
             scale_up_sfx: assets.sfx.scale_up.play_with_volume(0.0),
             scale_down_sfx: assets.sfx.scale_down.play_with_volume(0.0),
+            issues: Vec::new(),
+            share_code: String::new(),
+            svg_path: "level.svg".to_owned(),
+            generator_options: default(),
+            confirm_generate: false,
+            editor_error: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            watcher: default(),
+            next_reload_poll: 0.0,
+            config_reload_error: None,
+            level_reload_errors: default(),
             assets,
+            saved_config: config.clone(),
             config,
         };
         result.setup_level();
@@ -390,12 +570,59 @@ impl Game {
         if self.current_level == 0 {
             return;
         }
-        self.current_level -= 1;
+        self.switch_level(
+            self.current_pack,
+            self.current_campaign,
+            self.current_level - 1,
+        );
+    }
+
+    /// Asks what to do with unsaved changes first
+    fn switch_level(&mut self, pack: usize, campaign: usize, level: usize) {
+        if self.dirty {
+            self.pending_switch = Some((pack, campaign, level));
+            return;
+        }
+        self.current_pack = pack;
+        self.current_campaign = campaign;
+        self.current_level = level;
         self.setup_level();
     }
 
+    fn pack(&self) -> &pack::Pack {
+        &self.packs[self.current_pack]
+    }
+
+    fn campaign(&self) -> &campaign::Campaign {
+        &self.pack().levels.manifest.campaigns[self.current_campaign]
+    }
+
+    fn level_info(&self) -> &campaign::LevelInfo {
+        &self.campaign().levels[self.current_level]
+    }
+
+    fn complete_level(&mut self) {
+        let key = campaign::Progress::key(&self.pack().name, &self.level_info().name);
+        self.progress
+            .complete(key, physics::ticks_to_seconds(self.level_ticks));
+        if let (Some(replay), Some(run)) = (&mut self.replay, self.level_run.take()) {
+            replay.levels.push(run);
+            if let Some(path) = replay::last_run_path() {
+                if let Err(e) = replay.save(&path) {
+                    log::error!("Failed to save replay: {e}");
+                }
+            }
+        }
+        if self.dirty {
+            // Unsaved edits are being playtested, run them again instead of asking to leave
+            self.setup_level();
+            return;
+        }
+        self.next_level();
+    }
+
     fn next_level(&mut self) {
-        if self.current_level + 1 >= self.levels.list.len() {
+        if self.current_level + 1 >= self.campaign().levels.len() {
             self.assets
                 .sfx
                 .win
@@ -407,62 +634,862 @@ impl Game {
             .sfx
             .level
             .play_with_volume(self.config.sfx.level_volume);
-        self.current_level += 1;
-        self.setup_level();
+        self.switch_level(
+            self.current_pack,
+            self.current_campaign,
+            self.current_level + 1,
+        );
     }
 
     fn setup_level(&mut self) {
         self.finished = false;
-        self.level = self.levels.map[&self.levels.list[self.current_level]].clone();
+        // Only the current level can be dirty, switching away goes through `switch_level`
+        if !self.dirty {
+            self.level = self.pack().levels.map[&self.level_info().name].clone();
+        }
+        self.level_ticks = 0;
+        self.selection.clear();
+        self.polyline.clear();
+        if self
+            .replay
+            .as_ref()
+            .map_or(false, |replay| replay.pack != self.pack().name)
+        {
+            self.replay = Some(replay::Replay::new(&self.pack().name));
+        }
+        self.level_run = self
+            .replay
+            .is_some()
+            .then(|| replay::LevelRun::new(&self.level_info().name, &self.config, &self.level));
+        let track = self
+            .level_info()
+            .music
+            .as_ref()
+            .map(|track| self.pack().dir.join(track));
+        if let Some(path) = track {
+            if self.music_track.as_ref() != Some(&path) {
+                if let Some(sound) = self.music_tracks.get(&path) {
+                    self.music.stop();
+                    self.music = sound.play_with_volume(self.config.sfx.music_volume);
+                    self.music_track = Some(path);
+                }
+            }
+        }
         self.update_level();
-        self.player = Some(Player {
-            pos: self.level.start_pos,
-            vel: vec2::ZERO,
-            radius: self.config.player.radius,
-            r#static: 0.0,
-            scale_origin: vec2::ZERO,
-        });
+        self.player = Some(Player::new(&self.config, self.level.start_pos));
         self.camera.center = self.level.start_pos;
     }
 
-    fn save_level(&mut self) {
-        self.levels.map.insert(
-            self.levels.list[self.current_level].clone(),
-            self.level.clone(),
+    /// Stops recording the run since it no longer matches the shipped levels or physics
+    fn invalidate_replay(&mut self) {
+        self.replay = None;
+        self.level_run = None;
+    }
+
+    fn mark_dirty(&mut self) {
+        self.invalidate_replay();
+        if !self.dirty {
+            self.dirty = true;
+            self.unsaved_time = 0.0;
+        }
+    }
+
+    /// Returns whether the level was saved
+    fn save_level(&mut self) -> bool {
+        let name = self.level_info().name.clone();
+        let backups = self.config.editor.backups;
+        match self.packs[self.current_pack].save_level(&name, &self.level, backups) {
+            Ok(()) => {
+                self.dirty = false;
+                true
+            }
+            Err(e) => {
+                self.editor_error = Some(format!("Failed to save level: {e}"));
+                false
+            }
+        }
+    }
+
+    /// Drops unsaved edits together with their undo history
+    fn discard_changes(&mut self) {
+        self.dirty = false;
+        let key = (self.current_pack, self.level_info().name.clone());
+        self.histories.remove(&key);
+        self.selection.clear();
+        self.level = self.pack().levels.map[&key.1].clone();
+        self.update_level();
+    }
+
+    /// Propagates config values that are not read every frame
+    fn apply_config(&mut self) {
+        self.invalidate_replay();
+        self.camera.fov = Camera2dFov::MinSide(self.config.camera.fov);
+        self.geng
+            .audio()
+            .master_volume()
+            .set_value(self.config.sfx.master_volume);
+        self.music.set_volume(self.config.sfx.music_volume);
+        self.update_level();
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn hot_reload(&mut self) {
+        let paths = std::iter::once(config_path())
+            .chain(
+                self.packs
+                    .iter()
+                    .flat_map(|pack| hot_reload::level_files(&pack.dir)),
+            )
+            .collect::<Vec<_>>();
+        let changed = self.watcher.poll(paths);
+        if changed.is_empty() {
+            return;
+        }
+        if changed.contains(&config_path()) {
+            let result = futures::executor::block_on(file::load_detect::<Config>(config_path()))
+                .and_then(|config| {
+                    tuning::validate(&config)?;
+                    Ok(config)
+                });
+            match result {
+                Ok(config) => {
+                    log::info!("Reloaded config");
+                    self.saved_config = config.clone();
+                    self.config = config;
+                    self.config_reload_error = None;
+                    self.apply_config();
+                }
+                Err(e) => {
+                    self.config_reload_error = Some(format!("Failed to reload config: {e}"));
+                }
+            }
+        }
+        for index in 0..self.packs.len() {
+            let dir = self.packs[index].dir.clone();
+            if !changed.iter().any(|path| path.starts_with(&dir)) {
+                continue;
+            }
+            match futures::executor::block_on(Levels::load_dir(&dir)) {
+                Ok(levels) => {
+                    log::info!("Reloaded levels from {dir:?}");
+                    self.packs[index].levels = levels;
+                    self.level_reload_errors.remove(&index);
+                }
+                Err(e) => {
+                    self.level_reload_errors
+                        .insert(index, format!("Failed to reload levels: {e}"));
+                    continue;
+                }
+            }
+            if index == self.current_pack {
+                let campaigns = &self.pack().levels.manifest.campaigns;
+                self.current_campaign =
+                    self.current_campaign.min(campaigns.len().saturating_sub(1));
+                let levels = &campaigns[self.current_campaign].levels;
+                self.current_level = self.current_level.min(levels.len().saturating_sub(1));
+                let level = self.pack().levels.map[&self.level_info().name].clone();
+                // Saves from the editor come back here too, only external changes matter
+                if level != self.level && self.dirty {
+                    let error = format!(
+                        "{:?} changed on disk, save to overwrite or discard unsaved changes",
+                        self.level_info().name,
+                    );
+                    self.level_reload_errors.insert(index, error);
+                } else if level != self.level {
+                    // Keep the player where it is, only the geometry changes
+                    self.invalidate_replay();
+                    let key = (self.current_pack, self.level_info().name.clone());
+                    self.histories.remove(&key);
+                    self.selection.clear();
+                    self.level = level;
+                    self.update_level();
+                }
+            }
+        }
+    }
+
+    /// Runs a change of the current pack's levels and then switches to `current_level`
+    fn manage_levels(
+        &mut self,
+        current_level: usize,
+        f: impl FnOnce(&mut pack::Pack) -> anyhow::Result<()>,
+    ) -> bool {
+        if self.dirty && !self.save_level() {
+            return false;
+        }
+        match f(&mut self.packs[self.current_pack]) {
+            Ok(()) => {
+                self.editor_error = None;
+                self.confirm_delete = false;
+                self.current_level = current_level;
+                self.setup_level();
+                true
+            }
+            Err(e) => {
+                self.editor_error = Some(e.to_string());
+                false
+            }
+        }
+    }
+
+    fn create_level(&mut self, info: campaign::LevelInfo, level: Level) {
+        let campaign = self.current_campaign;
+        let index = self.current_level + 1;
+        if self.manage_levels(index, |pack| {
+            pack.create_level(campaign, index, info, level)
+        }) {
+            self.level_name.clear();
+        }
+    }
+
+    fn rename_level(&mut self) {
+        let old = self.level_info().name.clone();
+        let new = self.level_name.clone();
+        // Progress keys use the name the pack had before the operation
+        let from = campaign::Progress::key(&self.pack().name, &old);
+        let to = campaign::Progress::key(&self.pack().name, &new);
+        if self.manage_levels(self.current_level, |pack| pack.rename_level(&old, &new)) {
+            let pack = self.current_pack;
+            if let Some(history) = self.histories.remove(&(pack, old.clone())) {
+                self.histories.insert((pack, new.clone()), history);
+            }
+            self.progress.rename(&from, to);
+            self.level_name.clear();
+        }
+    }
+
+    fn delete_level(&mut self) {
+        let campaign = self.current_campaign;
+        let index = self.current_level;
+        let name = self.level_info().name.clone();
+        if self.manage_levels(index.saturating_sub(1), |pack| {
+            pack.delete_level(campaign, index)
+        }) {
+            self.histories.remove(&(self.current_pack, name));
+        }
+    }
+
+    fn move_level(&mut self, to: usize) {
+        let campaign = self.current_campaign;
+        let from = self.current_level;
+        if to < self.campaign().levels.len() {
+            self.manage_levels(to, |pack| pack.move_level(campaign, from, to));
+        }
+    }
+
+    /// Fits the editor camera around the points
+    fn frame(&mut self, points: impl IntoIterator<Item = vec2<f32>>) {
+        if let Some(bounds) = view::bounds(points) {
+            let (center, fov) = view::frame(bounds, self.framebuffer_size);
+            self.camera.center = center;
+            self.editor_fov = fov;
+        }
+    }
+
+    fn frame_level(&mut self) {
+        self.frame(view::level_points(&self.level).collect::<Vec<_>>());
+    }
+
+    /// Frames the whole level when nothing is selected
+    fn frame_selection(&mut self) {
+        let points: Vec<vec2<f32>> = self
+            .selection
+            .surfaces
+            .iter()
+            .flat_map(|&index| self.level.surfaces[index].ends)
+            .chain(self.selection.start.then_some(self.level.start_pos))
+            .chain(self.selection.finish.then_some(self.level.finish_pos))
+            .collect();
+        if points.is_empty() {
+            self.frame_level();
+        } else {
+            self.frame(points);
+        }
+    }
+
+    fn apply_cleanup(&mut self) {
+        if self
+            .cleanup_plan
+            .as_ref()
+            .map_or(false, |plan| plan.is_empty())
+        {
+            self.editor_error = Some("nothing to clean up".to_owned());
+            return;
+        }
+        let Some(plan) = self.cleanup_plan.take() else {
+            return;
+        };
+        self.selection.clear();
+        self.cleanup_preview = false;
+        self.edit(history::Command::replace(&self.level, plan.level));
+    }
+
+    fn history(&mut self) -> &mut history::History {
+        let key = (self.current_pack, self.level_info().name.clone());
+        self.histories.entry(key).or_default()
+    }
+
+    /// Applies an editor change so that it can be undone
+    fn edit(&mut self, command: history::Command) {
+        command.apply(&mut self.level);
+        self.history().push(command);
+        self.mark_dirty();
+        self.update_level();
+    }
+
+    /// Drags edit the level live and index into it, so history waits until they end
+    fn dragging(&self) -> bool {
+        self.selection.drag.is_some() || self.vertex_drag.is_some() || self.handle_drag.is_some()
+    }
+
+    fn undo(&mut self) {
+        if self.dragging() {
+            return;
+        }
+        let mut level = self.level.clone();
+        if self.history().undo(&mut level) {
+            self.level = level;
+            self.selection.clear();
+            self.mark_dirty();
+            self.update_level();
+        }
+    }
+
+    fn redo(&mut self) {
+        if self.dragging() {
+            return;
+        }
+        let mut level = self.level.clone();
+        if self.history().redo(&mut level) {
+            self.level = level;
+            self.selection.clear();
+            self.mark_dirty();
+            self.update_level();
+        }
+    }
+
+    fn select_press(&mut self, cursor: vec2<f32>, ctrl: bool, shift: bool, alt: bool) {
+        let mode = match self.hovered_surface(cursor) {
+            Some(index) if self.selection.surfaces.contains(&index) => {
+                if alt {
+                    selection::TransformMode::Rotate
+                } else if shift {
+                    selection::TransformMode::Scale
+                } else {
+                    selection::TransformMode::Move
+                }
+            }
+            Some(index) => {
+                if !shift {
+                    self.selection.clear();
+                }
+                self.selection.surfaces.insert(index);
+                selection::TransformMode::Move
+            }
+            None => {
+                if !shift {
+                    self.selection.clear();
+                }
+                self.selection.drag = Some(if ctrl {
+                    selection::Drag::Lasso {
+                        points: vec![cursor],
+                    }
+                } else {
+                    selection::Drag::Box { start: cursor }
+                });
+                return;
+            }
+        };
+        self.selection.drag = Some(selection::Drag::Transform {
+            mode,
+            start: cursor,
+            pivot: self.selection.pivot(&self.level).unwrap_or(cursor),
+            original: self
+                .selection
+                .surfaces
+                .iter()
+                .map(|&index| (index, self.level.surfaces[index].clone()))
+                .collect(),
+            original_start: self.selection.start.then_some(self.level.start_pos),
+            original_finish: self.selection.finish.then_some(self.level.finish_pos),
+        });
+    }
+
+    fn select_drag(&mut self, cursor: vec2<f32>) {
+        match &mut self.selection.drag {
+            Some(selection::Drag::Lasso { points }) => {
+                if (*points.last().unwrap() - cursor).len() > self.config.editor.snap_distance / 4.0
+                {
+                    points.push(cursor);
+                }
+            }
+            Some(selection::Drag::Transform {
+                mode,
+                start,
+                pivot,
+                original,
+                original_start,
+                original_finish,
+            }) => {
+                let transform = selection::transform(*mode, *start, *pivot, cursor);
+                for (index, surface) in original.iter() {
+                    self.level.surfaces[*index].ends = surface.ends.map(&transform);
+                }
+                if let Some(pos) = *original_start {
+                    self.level.start_pos = transform(pos);
+                }
+                if let Some(pos) = *original_finish {
+                    self.level.finish_pos = transform(pos);
+                }
+                self.update_level();
+            }
+            _ => {}
+        }
+    }
+
+    fn select_release(&mut self, cursor: vec2<f32>) {
+        let Some(drag) = self.selection.drag.take() else {
+            return;
+        };
+        if let Some(area) = drag.area(cursor) {
+            self.selection
+                .surfaces
+                .extend(selection::surfaces_in(&self.level, &area));
+            self.selection.start |= selection::point_in_polygon(self.level.start_pos, &area);
+            self.selection.finish |= selection::point_in_polygon(self.level.finish_pos, &area);
+        }
+        if let selection::Drag::Transform {
+            original,
+            original_start,
+            original_finish,
+            ..
+        } = drag
+        {
+            let changes: Vec<_> = original
+                .into_iter()
+                .map(|(index, before)| (index, before, self.level.surfaces[index].clone()))
+                .filter(|(_, before, after)| before != after)
+                .collect();
+            // Dragging already moved everything, put it back so the edit applies it again
+            for (index, before, _) in &changes {
+                self.level.surfaces[*index] = before.clone();
+            }
+            let mut commands = Vec::new();
+            if !changes.is_empty() {
+                commands.push(history::Command::ModifySurfaces(changes));
+            }
+            if let Some(before) = original_start.filter(|&pos| pos != self.level.start_pos) {
+                commands.push(history::Command::SetStart {
+                    before,
+                    after: std::mem::replace(&mut self.level.start_pos, before),
+                });
+            }
+            if let Some(before) = original_finish.filter(|&pos| pos != self.level.finish_pos) {
+                commands.push(history::Command::SetFinish {
+                    before,
+                    after: std::mem::replace(&mut self.level.finish_pos, before),
+                });
+            }
+            if !commands.is_empty() {
+                self.edit(history::Command::Batch(commands));
+            }
+        }
+    }
+
+    fn vertex_press(&mut self, cursor: vec2<f32>) {
+        if let Some(pos) = vertex::nearest(&self.level, cursor, self.config.editor.snap_distance) {
+            self.vertex_drag = Some(vertex::Drag::new(&self.level, pos, None));
+        } else if let Some(index) = self.hovered_surface(cursor) {
+            let pos = self.level.surfaces[index].to(cursor).closest_point;
+            let split = vertex::split(&self.level, index, pos);
+            split.apply(&mut self.level);
+            self.vertex_drag = Some(vertex::Drag::new(&self.level, pos, Some(split)));
+            self.update_level();
+        }
+    }
+
+    fn vertex_move(&mut self, cursor: vec2<f32>) {
+        let Some(drag) = &self.vertex_drag else {
+            return;
+        };
+        // Don't snap to the surfaces being dragged
+        let pos = self.snap(cursor, |index| drag.moves(index));
+        for &(index, end) in &drag.ends {
+            self.level.surfaces[index].ends[end] = pos;
+        }
+        self.update_level();
+    }
+
+    /// Start or finish within a few pixels of the cursor
+    fn hovered_handle(&self, cursor: vec2<f32>) -> Option<inspector::Target> {
+        let pick_radius = view::pixels_to_world(
+            view::HANDLE_PICK_RADIUS,
+            self.editor_fov,
+            self.framebuffer_size,
         );
-        serde_json::to_writer_pretty(
-            std::io::BufWriter::new(
-                std::fs::File::create(
-                    run_dir()
-                        .join("assets")
-                        .join("levels")
-                        .join(&self.levels.list[self.current_level])
-                        .with_extension("json"),
-                )
-                .unwrap(),
-            ),
-            &self.level,
-        )
-        .unwrap();
+        [
+            (inspector::Target::Start, self.level.start_pos),
+            (inspector::Target::Finish, self.level.finish_pos),
+        ]
+        .into_iter()
+        .find(|&(_, pos)| (cursor - pos).len() < pick_radius)
+        .map(|(target, _)| target)
     }
 
-    fn update_level(&mut self) {
-        self.level_mesh = LevelMesh::new(&self.geng, &self.config, &self.level);
+    fn handle_press(&mut self, cursor: vec2<f32>) {
+        if let Some(target) = self.hovered_handle(cursor) {
+            let values = inspector::Values::new(&self.level, &[target]);
+            self.handle_drag = Some((target, values));
+        }
     }
 
-    fn snapped(&self, screen_pos: vec2<f64>) -> vec2<f32> {
-        let world_pos = self.screen_to_world(screen_pos);
-        if let Some(p) = self
-            .level
+    fn handle_move(&mut self, pos: vec2<f32>) {
+        if let Some(&(target, _)) = self.handle_drag.as_ref() {
+            target.set(&mut self.level, pos);
+            self.update_level();
+        }
+    }
+
+    fn handle_release(&mut self) {
+        if let Some((_, original)) = self.handle_drag.take() {
+            let command = inspector::command(&mut self.level, &original);
+            self.edit(command);
+        }
+    }
+
+    fn vertex_release(&mut self) {
+        let Some(drag) = self.vertex_drag.take() else {
+            return;
+        };
+        let changes: Vec<_> = drag
+            .original
+            .into_iter()
+            .map(|(index, before)| (index, before, self.level.surfaces[index].clone()))
+            .filter(|(_, before, after)| before != after)
+            .collect();
+        // Undo the live preview so that the whole drag is applied as one edit
+        for (index, before, _) in &changes {
+            self.level.surfaces[*index] = before.clone();
+        }
+        if let Some(split) = &drag.split {
+            split.revert(&mut self.level);
+        }
+        let mut commands: Vec<_> = drag.split.into_iter().collect();
+        if !changes.is_empty() {
+            commands.push(history::Command::ModifySurfaces(changes));
+        }
+        if !commands.is_empty() {
+            self.selection.clear();
+            self.edit(history::Command::Batch(commands));
+        }
+    }
+
+    /// Clicking the first vertex closes the loop, clicking the last one again finishes
+    fn polyline_click(&mut self, pos: vec2<f32>) {
+        let close_to = |p: vec2<f32>| (p - pos).len() < self.config.editor.snap_distance;
+        match (self.polyline.first(), self.polyline.last()) {
+            (Some(&first), _) if self.polyline.len() >= 3 && close_to(first) => {
+                self.polyline.push(first);
+                self.finish_polyline();
+            }
+            (_, Some(&last)) if close_to(last) => self.finish_polyline(),
+            _ => self.polyline.push(pos),
+        }
+    }
+
+    fn finish_polyline(&mut self) {
+        let points = std::mem::take(&mut self.polyline);
+        if points.len() < 2 {
+            return;
+        }
+        self.edit(history::Command::AddSurfaces(
+            points
+                .windows(2)
+                .map(|ends| Surface {
+                    ends: [ends[0], ends[1]],
+                })
+                .collect(),
+        ));
+    }
+
+    /// Returns whether there was a vertex under the cursor
+    fn dissolve_vertex(&mut self, cursor: vec2<f32>) -> bool {
+        let Some(pos) = vertex::nearest(&self.level, cursor, self.config.editor.snap_distance)
+        else {
+            return false;
+        };
+        match vertex::dissolve(&self.level, pos, self.config.editor.snap_distance / 4.0) {
+            Ok(command) => {
+                self.editor_error = None;
+                self.selection.clear();
+                self.edit(command);
+            }
+            Err(e) => self.editor_error = Some(e),
+        }
+        true
+    }
+
+    /// Returns whether anything was copied
+    fn copy_selection(&mut self) -> bool {
+        let Some(fragment) = clipboard::Fragment::copy(&self.level, &self.selection) else {
+            return false;
+        };
+        let result = serde_json::to_string_pretty(&fragment)
+            .map_err(anyhow::Error::from)
+            .and_then(clipboard::set_text);
+        match result {
+            Ok(()) => {
+                self.editor_error = None;
+                true
+            }
+            Err(e) => {
+                self.editor_error = Some(format!("Failed to copy: {e}"));
+                false
+            }
+        }
+    }
+
+    /// Copies and removes the selected surfaces, markers can only be moved so they stay
+    fn cut_selection(&mut self) {
+        if self.copy_selection() {
+            self.delete_selection();
+        }
+    }
+
+    fn paste(&mut self, pos: vec2<f32>) {
+        let fragment = clipboard::get_text().and_then(|text| {
+            serde_json::from_str::<clipboard::Fragment>(&text)
+                .map_err(|e| anyhow::anyhow!("clipboard does not contain level surfaces: {e}"))
+        });
+        match fragment {
+            Ok(fragment) => {
+                self.editor_error = None;
+                let first = self.level.surfaces.len();
+                self.selection.clear();
+                self.selection.surfaces = (first..first + fragment.surfaces.len()).collect();
+                self.selection.start = fragment.start_pos.is_some();
+                self.selection.finish = fragment.finish_pos.is_some();
+                self.edit(fragment.paste(&self.level, pos));
+            }
+            Err(e) => self.editor_error = Some(format!("Failed to paste: {e}")),
+        }
+    }
+
+    fn save_prefab(&mut self) {
+        let Some(fragment) = clipboard::Fragment::copy(&self.level, &self.selection) else {
+            self.editor_error = Some("nothing selected".to_owned());
+            return;
+        };
+        match prefab::save(&self.prefab_name, &fragment) {
+            Ok(name) => {
+                self.editor_error = (name != self.prefab_name)
+                    .then(|| format!("A prefab with that name exists, saved as {name:?}"));
+                self.prefab_name.clear();
+                self.prefabs = prefab::load_all();
+            }
+            Err(e) => self.editor_error = Some(format!("Failed to save prefab: {e}")),
+        }
+    }
+
+    fn place_prefab(&mut self, pos: vec2<f32>) {
+        let Some(placement) = &self.placement else {
+            return;
+        };
+        let fragment = placement.transformed();
+        let first = self.level.surfaces.len();
+        self.selection.clear();
+        self.selection.surfaces = (first..first + fragment.surfaces.len()).collect();
+        self.edit(fragment.paste(&self.level, pos));
+    }
+
+    fn delete_selection(&mut self) {
+        if self.selection.surfaces.is_empty() {
+            return;
+        }
+        let indices = self.selection.surfaces.iter().copied().collect();
+        self.selection.clear();
+        self.edit(history::Command::remove_surfaces(&self.level, indices));
+    }
+
+    fn duplicate_selection(&mut self) {
+        if self.selection.surfaces.is_empty() {
+            return;
+        }
+        let offset = vec2(1.0, -1.0) * self.config.editor.snap_distance * 2.0;
+        let copies: Vec<Surface> = self
+            .selection
             .surfaces
             .iter()
-            .flat_map(|surface| surface.ends)
-            .filter(|&end| (end - world_pos).len() < self.config.editor.snap_distance)
-            .min_by_key(|&end| r32((end - world_pos).len()))
+            .map(|&index| Surface {
+                ends: self.level.surfaces[index].ends.map(|end| end + offset),
+            })
+            .collect();
+        let first = self.level.surfaces.len();
+        self.selection.surfaces = (first..first + copies.len()).collect();
+        self.edit(history::Command::AddSurfaces(copies));
+    }
+
+    fn update_level(&mut self) {
+        self.level_mesh = LevelMesh::new(&self.geng, &self.config, &self.level);
+        self.issues = validate::validate(&self.config, &self.level);
+        self.trajectory_stale = true;
+        self.update_cleanup_plan();
+    }
+
+    fn update_cleanup_plan(&mut self) {
+        self.cleanup_plan = self
+            .cleanup_preview
+            .then(|| cleanup::plan(&self.level, &self.cleanup));
+    }
+
+    /// Inverted band along the surface, as for the hovered one
+    fn draw_highlight(
+        &self,
+        framebuffer: &mut ugli::Framebuffer,
+        surface: &Surface,
+        uniforms: impl ugli::Uniforms,
+    ) {
+        let v = surface.ends[1] - surface.ends[0];
+        let matrix = mat3::translate((surface.ends[0] + surface.ends[1]) / 2.0)
+            * mat3::from_orts(
+                v.normalize_or_zero() * (v.len() / 2.0 + self.config.editor.snap_distance / 2.0),
+                v.normalize_or_zero().rotate_90() * self.config.editor.snap_distance / 2.0,
+            );
+        ugli::draw(
+            framebuffer,
+            &self.assets.shaders.selection,
+            ugli::DrawMode::TriangleFan,
+            &self.quad,
+            (
+                ugli::uniforms! {
+                    u_model_matrix: matrix,
+                },
+                uniforms,
+            ),
+            ugli::DrawParameters {
+                blend_mode: Some(ugli::BlendMode {
+                    rgb: ugli::ChannelBlendMode {
+                        src_factor: ugli::BlendFactor::OneMinusDstColor,
+                        dst_factor: ugli::BlendFactor::Zero,
+                        equation: ugli::BlendEquation::Add,
+                    },
+                    alpha: ugli::ChannelBlendMode {
+                        src_factor: ugli::BlendFactor::Zero,
+                        dst_factor: ugli::BlendFactor::One,
+                        equation: ugli::BlendEquation::Add,
+                    },
+                }),
+                ..default()
+            },
+        );
+    }
+
+    /// Square outline around a point
+    fn draw_marker(
+        &self,
+        framebuffer: &mut ugli::Framebuffer,
+        pos: vec2<f32>,
+        uniforms: impl ugli::Uniforms,
+    ) {
+        let matrix =
+            mat3::translate(pos) * mat3::scale_uniform(self.config.editor.snap_distance / 2.0);
+        ugli::draw(
+            framebuffer,
+            &self.assets.shaders.selection,
+            ugli::DrawMode::LineLoop { line_width: 2.0 },
+            &self.quad,
+            (
+                ugli::uniforms! {
+                    u_model_matrix: matrix,
+                },
+                uniforms,
+            ),
+            ugli::DrawParameters::default(),
+        );
+    }
+
+    /// Closed polyline in world coordinates
+    fn draw_outline(
+        &self,
+        framebuffer: &mut ugli::Framebuffer,
+        points: &[vec2<f32>],
+        uniforms: impl ugli::Uniforms,
+    ) {
+        let mode = ugli::DrawMode::LineLoop { line_width: 2.0 };
+        self.draw_lines(framebuffer, points, mode, uniforms);
+    }
+
+    /// Open polyline in world coordinates
+    fn draw_path(
+        &self,
+        framebuffer: &mut ugli::Framebuffer,
+        points: &[vec2<f32>],
+        uniforms: impl ugli::Uniforms,
+    ) {
+        let mode = ugli::DrawMode::LineStrip { line_width: 2.0 };
+        self.draw_lines(framebuffer, points, mode, uniforms);
+    }
+
+    fn draw_lines(
+        &self,
+        framebuffer: &mut ugli::Framebuffer,
+        points: &[vec2<f32>],
+        mode: ugli::DrawMode,
+        uniforms: impl ugli::Uniforms,
+    ) {
+        // Selection shader maps the unit quad onto [-1, 1]
+        let vertices = ugli::VertexBuffer::new_dynamic(
+            self.geng.ugli(),
+            points
+                .iter()
+                .map(|&p| Vertex {
+                    a_pos: (p + vec2::splat(1.0)) / 2.0,
+                })
+                .collect(),
+        );
+        ugli::draw(
+            framebuffer,
+            &self.assets.shaders.selection,
+            mode,
+            &vertices,
+            (
+                ugli::uniforms! {
+                    u_model_matrix: mat3::identity(),
+                },
+                uniforms,
+            ),
+            ugli::DrawParameters::default(),
+        );
+    }
+
+    /// Point a segment is being drawn from, for angle snapping
+    fn snap_anchor(&self) -> Option<vec2<f32>> {
+        self.start_draw.or(self.polyline.last().copied())
+    }
+
+    fn snap(&self, world_pos: vec2<f32>, ignore: impl Fn(usize) -> bool) -> vec2<f32> {
+        let window = self.geng.window();
+        if window.is_key_pressed(geng::Key::AltLeft) || window.is_key_pressed(geng::Key::AltRight) {
+            return world_pos;
+        }
+        let mut modes = self.snap_modes.clone();
+        if window.is_key_pressed(geng::Key::ShiftLeft)
+            || window.is_key_pressed(geng::Key::ShiftRight)
         {
-            return p;
+            modes.angle = !modes.angle;
         }
-        world_pos
+        snap::snap(
+            &modes,
+            &self.config.editor,
+            &self.level,
+            world_pos,
+            self.snap_anchor(),
+            ignore,
+        )
+    }
+
+    fn snapped(&self, screen_pos: vec2<f64>) -> vec2<f32> {
+        self.snap(self.screen_to_world(screen_pos), |_| false)
     }
 
     fn screen_to_world(&self, screen_pos: vec2<f64>) -> vec2<f32> {
@@ -470,28 +1497,442 @@ impl Game {
             .screen_to_world(self.framebuffer_size, screen_pos.map(|x| x as f32))
     }
 
-    fn ui(&mut self) {
-        if !self.cli.enable_editor {
-            return;
-        }
-        egui::Window::new("Editor").show(self.egui.clone().borrow().get_context(), |ui| {
-            ui.checkbox(&mut self.draw_insides, "draw insides");
-            ui.checkbox(&mut self.editor_mode, "Editor mode - F4");
-            if ui.button("prev level - [").clicked() {
-                self.prev_level();
+    fn ui(&mut self) {
+        if self.show_level_select {
+            self.level_select_ui();
+        }
+        if self.pending_switch.is_some() {
+            self.unsaved_changes_ui();
+        }
+        if self.show_minimap && self.editor_mode {
+            self.minimap_ui();
+        }
+        if !self.cli.enable_editor {
+            return;
+        }
+        if self.editor_mode {
+            self.inspector_ui();
+        }
+        egui::Window::new("Editor").show(self.egui.clone().borrow().get_context(), |ui| {
+            ui.checkbox(&mut self.draw_insides, "draw insides");
+            ui.checkbox(&mut self.editor_mode, "Editor mode - F4");
+            ui.horizontal(|ui| {
+                if ui
+                    .add_enabled(self.dirty, egui::Button::new("save - Ctrl+S"))
+                    .clicked()
+                {
+                    self.save_level();
+                }
+                if self.dirty {
+                    ui.label("unsaved changes");
+                }
+            });
+            if ui.button("prev level - [").clicked() {
+                self.prev_level();
+            }
+            if ui.button("next level - ]").clicked() {
+                self.next_level();
+            }
+            ui.horizontal(|ui| {
+                ui.selectable_value(&mut self.tool, Tool::Draw, "draw - 1");
+                ui.selectable_value(&mut self.tool, Tool::Select, "select - 2");
+                ui.selectable_value(&mut self.tool, Tool::Vertex, "vertex - 3");
+                ui.selectable_value(&mut self.tool, Tool::Polyline, "polyline - 4");
+                ui.selectable_value(&mut self.tool, Tool::Trajectory, "trajectory - 5");
+            });
+            ui.label("respawn at cursor - R");
+            match self.tool {
+                Tool::Draw => {
+                    ui.label("new segment - Drag LMB");
+                }
+                Tool::Select => {
+                    ui.label("select - LMB, box - Drag LMB, lasso - Ctrl+Drag LMB");
+                    ui.label("add to selection - Shift");
+                    ui.label("move - Drag selection");
+                    ui.label("rotate - Alt+Drag selection");
+                    ui.label("scale - Shift+Drag selection");
+                    ui.label("set pivot - P, reset pivot - Shift+P");
+                    ui.label("duplicate - Ctrl+D");
+                    ui.label("copy - Ctrl+C, cut - Ctrl+X");
+                    ui.label("delete selection - Delete");
+                }
+                Tool::Vertex => {
+                    ui.label("move vertex - Drag LMB on a vertex");
+                    ui.label("insert vertex - LMB on a segment");
+                    ui.label("dissolve vertex - RMB on a vertex");
+                }
+                Tool::Polyline => {
+                    ui.label("add vertex - LMB");
+                    ui.label("finish - Enter or click the last vertex again");
+                    ui.label("close loop - LMB on the first vertex");
+                    ui.label("remove last vertex - Backspace");
+                }
+                Tool::Trajectory => {
+                    ui.label("start point - LMB");
+                    self.trajectory_stale |= trajectory::controls_ui(
+                        ui,
+                        &mut self.trajectory,
+                        self.trajectory_path.as_ref(),
+                    );
+                }
+            }
+            ui.label("remove segment - RMB");
+            ui.label("paste at cursor - Ctrl+V");
+            ui.label("move start or finish - Drag LMB");
+            ui.label("set start - Z");
+            ui.label("set finish - X");
+            ui.label("save - Ctrl+S");
+            ui.collapsing("Camera", |ui| {
+                ui.label("pan - Drag MMB, zoom - Wheel");
+                ui.horizontal(|ui| {
+                    if ui.button("frame level - Home").clicked() {
+                        self.frame_level();
+                    }
+                    if ui.button("frame selection - F").clicked() {
+                        self.frame_selection();
+                    }
+                });
+                ui.checkbox(&mut self.show_minimap, "minimap");
+            });
+            ui.collapsing("Snapping", |ui| {
+                snap::modes_ui(ui, &mut self.snap_modes, &mut self.config.editor);
+            });
+            ui.horizontal(|ui| {
+                let history = self.history();
+                let (can_undo, can_redo) = (history.can_undo(), history.can_redo());
+                if ui
+                    .add_enabled(can_undo, egui::Button::new("undo - Ctrl+Z"))
+                    .clicked()
+                {
+                    self.undo();
+                }
+                if ui
+                    .add_enabled(can_redo, egui::Button::new("redo - Ctrl+Shift+Z"))
+                    .clicked()
+                {
+                    self.redo();
+                }
+            });
+            ui.collapsing("Levels", |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("name");
+                    ui.text_edit_singleline(&mut self.level_name);
+                });
+                ui.horizontal(|ui| {
+                    if ui.button("new").clicked() {
+                        let level = Level {
+                            start_pos: vec2::ZERO,
+                            finish_pos: vec2(5.0, 0.0),
+                            surfaces: Vec::new(),
+                            finish_radius: None,
+                        };
+                        self.create_level(campaign::LevelInfo::new(self.level_name.clone()), level);
+                    }
+                    if ui.button("duplicate").clicked() {
+                        let info = campaign::LevelInfo {
+                            name: self.level_name.clone(),
+                            title: None,
+                            ..self.level_info().clone()
+                        };
+                        self.create_level(info, self.level.clone());
+                    }
+                    if ui.button("rename").clicked() {
+                        self.rename_level();
+                    }
+                });
+                ui.horizontal(|ui| {
+                    if ui.button("move up").clicked() && self.current_level > 0 {
+                        self.move_level(self.current_level - 1);
+                    }
+                    if ui.button("move down").clicked() {
+                        self.move_level(self.current_level + 1);
+                    }
+                    ui.checkbox(&mut self.confirm_delete, "");
+                    if ui
+                        .add_enabled(self.confirm_delete, egui::Button::new("delete"))
+                        .clicked()
+                    {
+                        self.delete_level();
+                    }
+                });
+            });
+            ui.collapsing("Share code", |ui| {
+                ui.horizontal(|ui| {
+                    if ui.button("export").clicked() {
+                        self.share_code = share_code::encode(&self.level);
+                        self.editor_error = None;
+                    }
+                    if ui.button("import").clicked() {
+                        match share_code::decode(&self.share_code) {
+                            Ok(level) => {
+                                self.editor_error = None;
+                                self.edit(history::Command::replace(&self.level, level));
+                            }
+                            Err(e) => self.editor_error = Some(e.to_string()),
+                        }
+                    }
+                });
+                ui.add(egui::TextEdit::multiline(&mut self.share_code).desired_rows(3));
+            });
+            ui.collapsing("SVG", |ui| {
+                ui.text_edit_singleline(&mut self.svg_path);
+                ui.horizontal(|ui| {
+                    if ui.button("export").clicked() {
+                        let svg = svg::export(&self.level, &self.config, &default());
+                        self.editor_error = std::fs::write(&self.svg_path, svg)
+                            .err()
+                            .map(|e| e.to_string());
+                    }
+                    if ui.button("import").clicked() {
+                        let result = std::fs::read_to_string(&self.svg_path)
+                            .map_err(anyhow::Error::from)
+                            .and_then(|svg| svg::import(&svg, &default()));
+                        match result {
+                            Ok(level) => {
+                                self.editor_error = None;
+                                self.edit(history::Command::replace(&self.level, level));
+                            }
+                            Err(e) => self.editor_error = Some(e.to_string()),
+                        }
+                    }
+                });
+            });
+            ui.collapsing("Generate", |ui| {
+                let options = &mut self.generator_options;
+                ui.add(egui::DragValue::new(&mut options.seed).prefix("seed "));
+                ui.add(egui::Slider::new(&mut options.difficulty, 0.0..=1.0).text("difficulty"));
+                ui.add(egui::Slider::new(&mut options.sections, 1..=20).text("sections"));
+                ui.checkbox(&mut self.confirm_generate, "replace the current level");
+                if ui
+                    .add_enabled(self.confirm_generate, egui::Button::new("generate"))
+                    .clicked()
+                {
+                    self.confirm_generate = false;
+                    match generator::generate(&self.config, &self.generator_options) {
+                        Ok((seed, level)) => {
+                            // Next click tries a fresh level
+                            self.generator_options.seed = seed.wrapping_add(1);
+                            self.editor_error = None;
+                            self.edit(history::Command::replace(&self.level, level));
+                        }
+                        Err(e) => self.editor_error = Some(e.to_string()),
+                    }
+                }
+            });
+            ui.collapsing("Prefabs", |ui| {
+                ui.horizontal_wrapped(|ui| {
+                    for prefab in &self.prefabs {
+                        ui.vertical(|ui| {
+                            if prefab::thumbnail(ui, &prefab.fragment, 64.0) {
+                                self.placement =
+                                    Some(prefab::Placement::new(prefab.fragment.clone()));
+                                self.editor_mode = true;
+                            }
+                            ui.label(&prefab.name);
+                        });
+                    }
+                });
+                if self.placement.is_some() {
+                    ui.label("place - LMB, rotate - Q/E, mirror - M, stop - RMB");
+                }
+                ui.horizontal(|ui| {
+                    ui.text_edit_singleline(&mut self.prefab_name);
+                    if ui.button("save selection").clicked() {
+                        self.save_prefab();
+                    }
+                });
+            });
+            ui.collapsing("Clean up", |ui| {
+                let mut changed = false;
+                ui.horizontal(|ui| {
+                    changed |= ui
+                        .checkbox(&mut self.cleanup.merge, "merge endpoints closer than")
+                        .changed();
+                    changed |= ui
+                        .add(
+                            egui::DragValue::new(&mut self.cleanup.merge_distance)
+                                .speed(0.005)
+                                .clamp_range(0.001..=1.0),
+                        )
+                        .changed();
+                });
+                changed |= ui
+                    .checkbox(&mut self.cleanup.split, "split at intersections")
+                    .changed();
+                changed |= ui
+                    .checkbox(
+                        &mut self.cleanup.remove_dangling,
+                        "remove dangling surfaces",
+                    )
+                    .changed();
+                changed |= ui.checkbox(&mut self.cleanup_preview, "preview").changed();
+                if changed {
+                    self.update_cleanup_plan();
+                }
+                if let Some(plan) = &self.cleanup_plan {
+                    ui.label(plan.to_string());
+                }
+                if ui
+                    .add_enabled(self.cleanup_plan.is_some(), egui::Button::new("apply"))
+                    .clicked()
+                {
+                    self.apply_cleanup();
+                }
+            });
+            if let Some(error) = &self.editor_error {
+                ui.colored_label(egui::Color32::RED, error);
+            }
+            ui.separator();
+            if self.issues.is_empty() {
+                ui.label("no issues found");
+            }
+            for issue in &self.issues {
+                if ui.button(issue.to_string()).clicked() {
+                    self.camera.center = issue.pos;
+                }
+            }
+        });
+        egui::Window::new("Tuning").show(self.egui.clone().borrow().get_context(), |ui| {
+            if tuning::config_ui(ui, &mut self.config) {
+                self.apply_config();
+            }
+            ui.horizontal(|ui| {
+                if ui.button("save to config.toml").clicked() {
+                    match tuning::save(&self.config) {
+                        Ok(()) => self.saved_config = self.config.clone(),
+                        Err(e) => self.editor_error = Some(format!("Failed to save config: {e}")),
+                    }
+                }
+                if ui.button("revert").clicked() {
+                    self.config = self.saved_config.clone();
+                    self.apply_config();
+                }
+            });
+        });
+    }
+
+    /// Exact positions of the selection, a drag or typed value is a single edit
+    fn inspector_ui(&mut self) {
+        let targets = inspector::targets(&self.selection);
+        if targets.is_empty() {
+            return;
+        }
+        egui::Window::new("Inspector").show(self.egui.clone().borrow().get_context(), |ui| {
+            let before = inspector::Values::new(&self.level, &targets);
+            let (changed, active) = inspector::ui(ui, &self.config, &mut self.level, &targets);
+            if changed {
+                self.inspector_original.get_or_insert(before);
+                self.update_level();
+            }
+            if !active {
+                if let Some(original) = self.inspector_original.take() {
+                    let command = inspector::command(&mut self.level, &original);
+                    self.edit(command);
+                }
+            }
+        });
+    }
+
+    fn minimap_ui(&mut self) {
+        let mut open = true;
+        egui::Window::new("Minimap").open(&mut open).show(
+            self.egui.clone().borrow().get_context(),
+            |ui| {
+                if let Some(pos) =
+                    view::minimap(ui, &self.level, &self.camera, self.framebuffer_size, 200.0)
+                {
+                    self.camera.center = pos;
+                }
+            },
+        );
+        self.show_minimap = open;
+    }
+
+    fn level_select_ui(&mut self) {
+        egui::Window::new("Levels").show(self.egui.clone().borrow().get_context(), |ui| {
+            let mut selected_pack = self.current_pack;
+            egui::ComboBox::from_label("Level pack")
+                .selected_text(&self.pack().name)
+                .show_ui(ui, |ui| {
+                    for (index, pack) in self.packs.iter().enumerate() {
+                        ui.selectable_value(&mut selected_pack, index, &pack.name);
+                    }
+                });
+            if selected_pack != self.current_pack {
+                self.switch_level(selected_pack, 0, 0);
             }
-            if ui.button("next level - ]").clicked() {
-                self.next_level();
+            if self.pack().read_only && self.cli.enable_editor {
+                ui.label("edits will be saved into a copy of this pack");
+            }
+            let pack = &self.packs[self.current_pack];
+            let mut selected = None;
+            for campaign_index in 0..pack.levels.manifest.campaigns.len() {
+                let campaign = &pack.levels.manifest.campaigns[campaign_index];
+                ui.heading(campaign.title());
+                for (level_index, level) in campaign.levels.iter().enumerate() {
+                    let unlocked = self.cli.enable_editor
+                        || self.progress.is_unlocked(
+                            &pack.name,
+                            &pack.levels.manifest,
+                            campaign_index,
+                            level_index,
+                        );
+                    ui.horizontal(|ui| {
+                        if ui
+                            .add_enabled(unlocked, egui::Button::new(level.title()))
+                            .clicked()
+                        {
+                            selected = Some((campaign_index, level_index));
+                        }
+                        if let Some(author) = &level.author {
+                            ui.label(format!("by {author}"));
+                        }
+                        if let Some(best) = self
+                            .progress
+                            .best_times
+                            .get(&campaign::Progress::key(&pack.name, &level.name))
+                        {
+                            ui.label(format!("best {}", format_time(*best)));
+                        }
+                        if let Some(par) = level.par_time {
+                            ui.label(format!("par {}", format_time(par)));
+                        }
+                    });
+                }
+            }
+            if let Some((campaign_index, level_index)) = selected {
+                self.show_level_select = false;
+                self.switch_level(self.current_pack, campaign_index, level_index);
             }
-            ui.label("respawn at cursor - R");
-            ui.label("new segment - Drag LMB");
-            ui.label("remove segment - RMB");
-            ui.label("set start - Z");
-            ui.label("set finish - X");
-            ui.label("level saves automatically");
         });
     }
 
+    fn unsaved_changes_ui(&mut self) {
+        egui::Window::new("Unsaved changes")
+            .collapsible(false)
+            .show(self.egui.clone().borrow().get_context(), |ui| {
+                ui.label(format!("{} has unsaved changes", self.level_info().title()));
+                ui.horizontal(|ui| {
+                    let mut proceed = false;
+                    if ui.button("save").clicked() {
+                        proceed = self.save_level();
+                    }
+                    if ui.button("discard").clicked() {
+                        self.discard_changes();
+                        proceed = true;
+                    }
+                    if ui.button("cancel").clicked() {
+                        self.pending_switch = None;
+                    }
+                    if proceed {
+                        if let Some((pack, campaign, level)) = self.pending_switch.take() {
+                            self.switch_level(pack, campaign, level);
+                        }
+                    }
+                });
+            });
+    }
+
     fn hovered_surface(&self, cursor: vec2<f32>) -> Option<usize> {
         self.level
             .surfaces
@@ -504,81 +1945,48 @@ impl Game {
             .map(|(index, _)| index)
     }
 
-    fn tick(&mut self, delta_time: f32) {
-        let cursor_pos =
-            self.screen_to_world(self.geng.window().cursor_position().unwrap_or(vec2::ZERO));
-        if let Some(player) = &mut self.player {
-            player.r#static =
-                (player.r#static + delta_time / self.config.r#static.time_to_full).min(1.0);
-            if player.vel.len() > self.config.r#static.max_vel {
-                player.r#static = 0.0;
-            }
-            player.vel.y -= self.config.gravity * delta_time * (1.0 - player.r#static);
-            player.pos += player.vel * delta_time * (1.0 - player.r#static);
-
-            let target_radius = if self
-                .geng
-                .window()
-                .is_button_pressed(geng::MouseButton::Left)
-            {
-                self.config.player.max_radius
-            } else if self
-                .geng
-                .window()
-                .is_button_pressed(geng::MouseButton::Right)
-            {
-                self.config.player.min_radius
+    fn input(&self) -> physics::Input {
+        let window = self.geng.window();
+        physics::Input {
+            scaling: if window.is_button_pressed(geng::MouseButton::Left) {
+                physics::Scaling::Grow
+            } else if window.is_button_pressed(geng::MouseButton::Right) {
+                physics::Scaling::Shrink
             } else {
-                self.config.player.radius
-            };
-            let scaling_speed = (target_radius - player.radius) * self.config.player.scaling_speed;
-            self.scale_up_sfx
-                .set_volume((scaling_speed / self.config.sfx.scaling_max_volume).clamp(0.0, 1.0));
-            self.scale_down_sfx
-                .set_volume((-scaling_speed / self.config.sfx.scaling_max_volume).clamp(0.0, 1.0));
-            if scaling_speed.abs() > self.config.r#static.max_vel {
-                player.r#static = 0.0;
-            }
-            let old_radius = player.radius;
-            let scale_origin = player.pos + (cursor_pos - player.pos).clamp_len(..=player.radius);
-            player.scale_origin = scale_origin;
-            let new_radius = (player.radius + scaling_speed * delta_time)
-                .clamp(self.config.player.min_radius, self.config.player.max_radius);
-            player.pos = scale_origin + (player.pos - scale_origin) * new_radius / old_radius;
-            player.radius = new_radius;
-
-            for surface in &self.level.surfaces {
-                let to = surface.to(player.pos);
-                if to.distance < player.radius {
-                    let penetration = player.radius - to.distance;
-                    player.pos += to.normal * penetration;
-                    player.radius -= penetration;
-                    let vel_at_collision_point =
-                        player.vel + scaling_speed * (to.closest_point - scale_origin) / old_radius;
-                    let normal_vel = vec2::dot(vel_at_collision_point, to.normal);
-                    if normal_vel < 0.0 {
-                        player.vel -= to.normal * normal_vel * (1.0 + self.config.bounciness);
-                        let sfx_volume =
-                            (-normal_vel / self.config.sfx.hit_max_volume_speed).clamp(0.0, 1.0);
-                        if sfx_volume > 0.1 {
-                            self.assets
-                                .sfx
-                                .hit
-                                .play_with_volume(sfx_volume * self.config.sfx.hit_volume)
-                                .set_speed(thread_rng().gen_range(0.8..1.2));
-                        }
-                    }
-                    let along = to.normal.rotate_90();
-                    let along_vel = vec2::dot(vel_at_collision_point, along);
-                    player.vel -=
-                        along * along_vel.clamp_abs(normal_vel.abs() * self.config.friction);
+                physics::Scaling::None
+            },
+            cursor: self.screen_to_world(window.cursor_position().unwrap_or(vec2::ZERO)),
+        }
+    }
+
+    fn tick(&mut self) {
+        let input = self.input();
+        if let Some(player) = &mut self.player {
+            let events = player.step(&self.config, &self.level, &input);
+            self.level_ticks += 1;
+            self.run_ticks += 1;
+            if let Some(run) = &mut self.level_run {
+                run.record(&input, player);
+            }
+            self.scale_up_sfx.set_volume(
+                (events.scaling_speed / self.config.sfx.scaling_max_volume).clamp(0.0, 1.0),
+            );
+            self.scale_down_sfx.set_volume(
+                (-events.scaling_speed / self.config.sfx.scaling_max_volume).clamp(0.0, 1.0),
+            );
+            for normal_vel in events.hits {
+                let sfx_volume =
+                    (normal_vel / self.config.sfx.hit_max_volume_speed).clamp(0.0, 1.0);
+                if sfx_volume > 0.1 {
+                    self.assets
+                        .sfx
+                        .hit
+                        .play_with_volume(sfx_volume * self.config.sfx.hit_volume)
+                        .set_speed(thread_rng().gen_range(0.8..1.2));
                 }
             }
-
-            if (player.pos - self.level.finish_pos).len()
-                < player.radius + self.config.finish_radius
-            {
-                self.next_level();
+            if events.finished {
+                self.complete_level();
             }
         }
     }
@@ -587,28 +1995,58 @@ impl Game {
 impl geng::State for Game {
     fn update(&mut self, delta_time: f64) {
         let delta_time = delta_time as f32;
+        self.next_reload_poll -= delta_time;
+        if self.next_reload_poll < 0.0 {
+            self.next_reload_poll = 0.5;
+            #[cfg(not(target_arch = "wasm32"))]
+            self.hot_reload();
+        }
         if self.finished {
             self.scale_down_sfx.set_volume(0.0);
             self.scale_up_sfx.set_volume(0.0);
             return;
         }
         self.time += delta_time;
+        if self.dirty {
+            self.unsaved_time += delta_time;
+            let interval = self.config.editor.autosave_interval;
+            if interval > 0.0 && self.unsaved_time >= interval {
+                self.save_level();
+                self.unsaved_time = 0.0;
+            }
+        }
         if self.editor_mode {
             self.player = None;
-            if self.geng.window().is_key_pressed(geng::Key::W) {
-                self.camera.center.y += self.config.editor.camera_speed * delta_time;
+            self.camera.fov = Camera2dFov::MinSide(self.editor_fov);
+            if self.tool == Tool::Trajectory && self.trajectory_stale {
+                self.trajectory_path = self.trajectory.simulate(&self.config, &self.level);
+                self.trajectory_stale = false;
             }
-            if self.geng.window().is_key_pressed(geng::Key::A) {
-                self.camera.center.x -= self.config.editor.camera_speed * delta_time;
-            }
-            if self.geng.window().is_key_pressed(geng::Key::S) {
-                self.camera.center.y -= self.config.editor.camera_speed * delta_time;
+            // Same speed on screen at any zoom
+            let speed = self.config.editor.camera_speed * self.editor_fov / self.config.camera.fov;
+            let window = self.geng.window();
+            let ctrl = window.is_key_pressed(geng::Key::ControlLeft)
+                || window.is_key_pressed(geng::Key::ControlRight);
+            // Ctrl+S or typing a level name should not move the camera
+            if !ctrl && !self.egui.borrow().get_context().wants_keyboard_input() {
+                if window.is_key_pressed(geng::Key::W) {
+                    self.camera.center.y += speed * delta_time;
+                }
+                if window.is_key_pressed(geng::Key::A) {
+                    self.camera.center.x -= speed * delta_time;
+                }
+                if window.is_key_pressed(geng::Key::S) {
+                    self.camera.center.y -= speed * delta_time;
+                }
+                if window.is_key_pressed(geng::Key::D) {
+                    self.camera.center.x += speed * delta_time;
+                }
             }
-            if self.geng.window().is_key_pressed(geng::Key::D) {
-                self.camera.center.x += self.config.editor.camera_speed * delta_time;
+        } else {
+            self.camera.fov = Camera2dFov::MinSide(self.config.camera.fov);
+            if self.player.is_none() {
+                self.setup_level();
             }
-        } else if self.player.is_none() {
-            self.setup_level();
         }
 
         if let Some(player) = &self.player {
@@ -617,13 +2055,9 @@ impl geng::State for Game {
         }
         if self.player.is_some() {
             self.unprocessed += delta_time;
-            while self.unprocessed > 0.0 {
-                let vel = self.player.as_ref().unwrap().vel.len();
-                let dt = self
-                    .unprocessed
-                    .min(self.config.tick_distance / vel.max(1.0));
-                self.tick(dt);
-                self.unprocessed -= dt;
+            while self.unprocessed >= physics::TICK && !self.finished {
+                self.tick();
+                self.unprocessed -= physics::TICK;
             }
         }
     }
@@ -747,6 +2181,25 @@ impl geng::State for Game {
             },
         );
 
+        if self.editor_mode && self.snap_modes.grid {
+            ugli::draw(
+                framebuffer,
+                &self.assets.shaders.grid,
+                ugli::DrawMode::TriangleFan,
+                &self.quad,
+                (
+                    ugli::uniforms! {
+                        u_spacing: self.config.editor.grid_spacing,
+                    },
+                    &uniforms,
+                ),
+                ugli::DrawParameters {
+                    blend_mode: Some(ugli::BlendMode::premultiplied_alpha()),
+                    ..default()
+                },
+            );
+        }
+
         if let Some(player) = &self.player {
             ugli::draw(
                 framebuffer,
@@ -778,7 +2231,7 @@ impl geng::State for Game {
             (
                 ugli::uniforms! {
                     u_pos: self.level.finish_pos,
-                    u_radius: self.config.finish_radius,
+                    u_radius: self.level.finish_radius(&self.config),
                 },
                 &uniforms,
             ),
@@ -809,11 +2262,128 @@ impl geng::State for Game {
                 },
             );
 
+            for issue in &self.issues {
+                let matrix = mat3::translate(issue.pos)
+                    * mat3::rotate(Angle::from_degrees(45.0))
+                    * mat3::scale_uniform(self.config.editor.snap_distance);
+                ugli::draw(
+                    framebuffer,
+                    &self.assets.shaders.selection,
+                    ugli::DrawMode::LineLoop { line_width: 2.0 },
+                    &self.quad,
+                    (
+                        ugli::uniforms! {
+                            u_model_matrix: matrix,
+                        },
+                        &uniforms,
+                    ),
+                    ugli::DrawParameters::default(),
+                );
+            }
+
             let cursor =
                 self.screen_to_world(self.geng.window().cursor_position().unwrap_or(vec2::ZERO));
             let snapped_cursor =
                 self.snapped(self.geng.window().cursor_position().unwrap_or(vec2::ZERO));
 
+            for &index in &self.selection.surfaces {
+                self.draw_highlight(framebuffer, &self.level.surfaces[index], &uniforms);
+            }
+            for (selected, pos) in [
+                (self.selection.start, self.level.start_pos),
+                (self.selection.finish, self.level.finish_pos),
+            ] {
+                if selected {
+                    self.draw_marker(framebuffer, pos, &uniforms);
+                }
+            }
+            let handle = self
+                .handle_drag
+                .as_ref()
+                .map(|&(target, _)| target)
+                .or_else(|| self.hovered_handle(cursor));
+            if let Some(target) = handle {
+                self.draw_marker(framebuffer, target.get(&self.level), &uniforms);
+            }
+            if let Some(area) = self
+                .selection
+                .drag
+                .as_ref()
+                .and_then(|drag| drag.area(cursor))
+            {
+                self.draw_outline(framebuffer, &area, &uniforms);
+            }
+            if let Some(&last) = self.polyline.last() {
+                for ends in self.polyline.windows(2) {
+                    let surface = Surface {
+                        ends: [ends[0], ends[1]],
+                    };
+                    self.draw_highlight(framebuffer, &surface, &uniforms);
+                }
+                let preview = Surface {
+                    ends: [last, snapped_cursor],
+                };
+                self.draw_highlight(framebuffer, &preview, &uniforms);
+            }
+
+            if let Some(plan) = &self.cleanup_plan {
+                for surface in &plan.removed {
+                    self.draw_highlight(framebuffer, surface, &uniforms);
+                }
+                for &pos in plan.splits.iter().chain(&plan.merged) {
+                    self.draw_marker(framebuffer, pos, &uniforms);
+                }
+            }
+
+            if let Some(path) = self
+                .trajectory_path
+                .as_ref()
+                .filter(|_| self.tool == Tool::Trajectory)
+            {
+                let points: Vec<vec2<f32>> = path.states.iter().map(|&(pos, _)| pos).collect();
+                self.draw_path(framebuffer, &points, &uniforms);
+                let outlines = path.states.iter().step_by(trajectory::OUTLINE_INTERVAL);
+                for &(pos, radius) in outlines.chain(path.states.last()) {
+                    let circle: Vec<vec2<f32>> = (0..24)
+                        .map(|i| {
+                            let angle = Angle::from_degrees(i as f32 * 15.0);
+                            pos + vec2(radius, 0.0).rotate(angle)
+                        })
+                        .collect();
+                    self.draw_outline(framebuffer, &circle, &uniforms);
+                }
+            }
+
+            if let Some(placement) = &self.placement {
+                let fragment = placement.transformed();
+                for surface in &fragment.surfaces {
+                    let surface = Surface {
+                        ends: surface.ends.map(|end| end + snapped_cursor),
+                    };
+                    self.draw_highlight(framebuffer, &surface, &uniforms);
+                }
+                for pos in fragment.start_pos.into_iter().chain(fragment.finish_pos) {
+                    self.draw_marker(framebuffer, pos + snapped_cursor, &uniforms);
+                }
+            }
+
+            let marker = match self.tool {
+                Tool::Draw => None,
+                Tool::Polyline => self.polyline.first().copied(),
+                Tool::Trajectory => self.trajectory.start,
+                Tool::Select => self.selection.pivot(&self.level),
+                Tool::Vertex => match &self.vertex_drag {
+                    Some(drag) => {
+                        let (index, end) = drag.ends[0];
+                        Some(self.level.surfaces[index].ends[end])
+                    }
+                    None => vertex::nearest(&self.level, cursor, self.config.editor.snap_distance),
+                },
+            };
+            if let Some(pos) = marker {
+                self.draw_marker(framebuffer, pos, &uniforms);
+            }
+
             if let Some(start) = self.start_draw {
                 let v = snapped_cursor - start;
                 let matrix = mat3::translate((snapped_cursor + start) / 2.0)
@@ -839,43 +2409,11 @@ impl geng::State for Game {
                     },
                 );
             } else {
-                if let Some(surface) = self.hovered_surface(cursor) {
-                    let surface = &self.level.surfaces[surface];
-                    let v = surface.ends[1] - surface.ends[0];
-                    let matrix = mat3::translate((surface.ends[0] + surface.ends[1]) / 2.0)
-                        * mat3::from_orts(
-                            v.normalize_or_zero()
-                                * (v.len() / 2.0 + self.config.editor.snap_distance / 2.0),
-                            v.normalize_or_zero().rotate_90() * self.config.editor.snap_distance
-                                / 2.0,
-                        );
-                    ugli::draw(
-                        framebuffer,
-                        &self.assets.shaders.selection,
-                        ugli::DrawMode::TriangleFan,
-                        &self.quad,
-                        (
-                            ugli::uniforms! {
-                                u_model_matrix: matrix,
-                            },
-                            &uniforms,
-                        ),
-                        ugli::DrawParameters {
-                            blend_mode: Some(ugli::BlendMode {
-                                rgb: ugli::ChannelBlendMode {
-                                    src_factor: ugli::BlendFactor::OneMinusDstColor,
-                                    dst_factor: ugli::BlendFactor::Zero,
-                                    equation: ugli::BlendEquation::Add,
-                                },
-                                alpha: ugli::ChannelBlendMode {
-                                    src_factor: ugli::BlendFactor::Zero,
-                                    dst_factor: ugli::BlendFactor::One,
-                                    equation: ugli::BlendEquation::Add,
-                                },
-                            }),
-                            ..default()
-                        },
-                    );
+                if let Some(surface) = self
+                    .hovered_surface(cursor)
+                    .filter(|index| !self.selection.surfaces.contains(index))
+                {
+                    self.draw_highlight(framebuffer, &self.level.surfaces[surface], &uniforms);
                 }
                 if true {
                     let cursor_matrix = mat3::translate(snapped_cursor)
@@ -921,12 +2459,7 @@ impl geng::State for Game {
                 rotation: Angle::ZERO,
                 fov: Camera2dFov::Vertical(10.0),
             },
-            &{
-                let ms = (self.time * 1000.0) as i64;
-                let seconds = ms / 1000;
-                let minutes = seconds / 60;
-                format!("{}:{:02}:{:03}", minutes, seconds % 60, ms % 1000)
-            },
+            &format_time(physics::ticks_to_seconds(self.run_ticks)),
             vec2(geng::TextAlign::CENTER, geng::TextAlign::TOP),
             mat3::translate(vec2(0.0, 5.0)),
             Rgba::WHITE,
@@ -934,6 +2467,59 @@ impl geng::State for Game {
             Rgba::BLACK,
         );
 
+        let hud_camera = Camera2d {
+            center: vec2::ZERO,
+            rotation: Angle::ZERO,
+            fov: Camera2dFov::Vertical(10.0),
+        };
+        let info = self.level_info();
+        let title = match info.par_time {
+            Some(par) => format!("{} - par {}", info.title(), format_time(par)),
+            None => info.title().to_owned(),
+        };
+        self.assets.font.draw_with_outline(
+            framebuffer,
+            &hud_camera,
+            &title,
+            vec2(geng::TextAlign::CENTER, geng::TextAlign::TOP),
+            mat3::translate(vec2(0.0, 4.0)) * mat3::scale_uniform(0.5),
+            Rgba::WHITE,
+            0.05,
+            Rgba::BLACK,
+        );
+        if let Some(hint) = &info.hint {
+            self.assets.font.draw_with_outline(
+                framebuffer,
+                &hud_camera,
+                hint,
+                vec2(geng::TextAlign::CENTER, geng::TextAlign::BOTTOM),
+                mat3::translate(vec2(0.0, -4.8)) * mat3::scale_uniform(0.4),
+                Rgba::WHITE,
+                0.05,
+                Rgba::BLACK,
+            );
+        }
+
+        let reload_errors = self
+            .config_reload_error
+            .iter()
+            .chain(self.level_reload_errors.values());
+        for (line, error) in reload_errors.enumerate() {
+            self.assets.font.draw_with_outline(
+                framebuffer,
+                &hud_camera,
+                error,
+                vec2(geng::TextAlign::LEFT, geng::TextAlign::BOTTOM),
+                mat3::translate(vec2(
+                    -4.8 * self.framebuffer_size.x / self.framebuffer_size.y,
+                    -4.8 + line as f32 * 0.4,
+                )) * mat3::scale_uniform(0.3),
+                Rgba::RED,
+                0.05,
+                Rgba::BLACK,
+            );
+        }
+
         if self.finished {
             self.assets.font.draw_with_outline(
                 framebuffer,
@@ -961,12 +2547,22 @@ impl geng::State for Game {
         if self.egui.borrow().get_context().is_pointer_over_area() {
             return;
         }
+        if matches!(
+            event,
+            geng::Event::KeyPress {
+                key: geng::Key::Escape
+            }
+        ) {
+            self.show_level_select = !self.show_level_select;
+            return;
+        }
         if matches!(event, geng::Event::KeyPress { key: geng::Key::R })
             && self.geng.window().is_key_pressed(geng::Key::ControlLeft)
         {
             self.time = 0.0;
-            self.current_level = 0;
-            self.setup_level();
+            self.run_ticks = 0;
+            self.replay = Some(replay::Replay::new(&self.pack().name));
+            self.switch_level(self.current_pack, self.current_campaign, 0);
             self.editor_mode = false;
             return;
         }
@@ -977,13 +2573,9 @@ impl geng::State for Game {
                     geng::Key::R => {
                         if let Some(screen_pos) = self.geng.window().cursor_position() {
                             self.editor_mode = false;
-                            self.player = Some(Player {
-                                pos: self.screen_to_world(screen_pos),
-                                vel: vec2::ZERO,
-                                radius: self.config.player.radius,
-                                r#static: 0.0,
-                                scale_origin: vec2::ZERO,
-                            });
+                            self.invalidate_replay();
+                            self.player =
+                                Some(Player::new(&self.config, self.screen_to_world(screen_pos)));
                         }
                     }
                     geng::Key::BracketLeft => self.prev_level(),
@@ -994,28 +2586,143 @@ impl geng::State for Game {
             }
         }
         if self.editor_mode {
+            let window = self.geng.window();
+            let ctrl = window.is_key_pressed(geng::Key::ControlLeft)
+                || window.is_key_pressed(geng::Key::ControlRight);
+            let shift = window.is_key_pressed(geng::Key::ShiftLeft)
+                || window.is_key_pressed(geng::Key::ShiftRight);
+            let alt = window.is_key_pressed(geng::Key::AltLeft)
+                || window.is_key_pressed(geng::Key::AltRight);
+            let cursor = self.screen_to_world(window.cursor_position().unwrap_or(vec2::ZERO));
             match event {
                 geng::Event::MousePress {
-                    button: geng::MouseButton::Left,
+                    button: geng::MouseButton::Middle,
+                } => {
+                    self.pan = Some(cursor);
+                    return;
+                }
+                geng::Event::MouseRelease {
+                    button: geng::MouseButton::Middle,
+                } => {
+                    self.pan = None;
+                    return;
+                }
+                geng::Event::CursorMove { .. } if self.pan.is_some() => {
+                    self.camera.center += self.pan.unwrap() - cursor;
+                    return;
+                }
+                geng::Event::Wheel { delta } => {
+                    view::zoom(
+                        &mut self.camera,
+                        &mut self.editor_fov,
+                        self.framebuffer_size,
+                        window
+                            .cursor_position()
+                            .unwrap_or(vec2::ZERO)
+                            .map(|x| x as f32),
+                        delta as f32,
+                    );
+                    return;
+                }
+                geng::Event::KeyPress {
+                    key: geng::Key::Home,
                 } => {
-                    self.start_draw = self
-                        .geng
-                        .window()
-                        .cursor_position()
-                        .map(|pos| self.snapped(pos));
+                    self.frame_level();
+                    return;
+                }
+                geng::Event::KeyPress { key: geng::Key::F } => {
+                    self.frame_selection();
+                    return;
+                }
+                _ => {}
+            }
+            if self.placement.is_some() {
+                match event {
+                    geng::Event::MousePress {
+                        button: geng::MouseButton::Left,
+                    } => {
+                        let pos = self.snapped(window.cursor_position().unwrap_or(vec2::ZERO));
+                        self.place_prefab(pos);
+                    }
+                    geng::Event::MousePress {
+                        button: geng::MouseButton::Right,
+                    } => self.placement = None,
+                    geng::Event::KeyPress { key } => {
+                        let step = self.config.editor.angle_step;
+                        let placement = self.placement.as_mut().unwrap();
+                        match key {
+                            geng::Key::Q => placement.rotation += step,
+                            geng::Key::E => placement.rotation -= step,
+                            geng::Key::M => placement.mirrored = !placement.mirrored,
+                            _ => {}
+                        }
+                    }
+                    _ => {}
+                }
+                return;
+            }
+            match event {
+                geng::Event::MousePress {
+                    button: geng::MouseButton::Left,
+                } if self.tool != Tool::Trajectory && self.hovered_handle(cursor).is_some() => {
+                    self.handle_press(cursor)
                 }
+                geng::Event::CursorMove { .. } if self.handle_drag.is_some() => {
+                    let pos = self.snapped(window.cursor_position().unwrap_or(vec2::ZERO));
+                    self.handle_move(pos);
+                }
+                geng::Event::MouseRelease {
+                    button: geng::MouseButton::Left,
+                } if self.handle_drag.is_some() => self.handle_release(),
+                geng::Event::MousePress {
+                    button: geng::MouseButton::Left,
+                } => match self.tool {
+                    Tool::Draw => {
+                        self.start_draw = self
+                            .geng
+                            .window()
+                            .cursor_position()
+                            .map(|pos| self.snapped(pos));
+                    }
+                    Tool::Select => self.select_press(cursor, ctrl, shift, alt),
+                    Tool::Vertex => self.vertex_press(cursor),
+                    Tool::Polyline => {
+                        let pos = self.snapped(window.cursor_position().unwrap_or(vec2::ZERO));
+                        self.polyline_click(pos);
+                    }
+                    Tool::Trajectory => {
+                        self.trajectory.start = Some(cursor);
+                        self.trajectory_stale = true;
+                    }
+                },
+                geng::Event::CursorMove { .. } => match self.tool {
+                    Tool::Draw | Tool::Polyline => {}
+                    Tool::Trajectory => {
+                        if window.is_button_pressed(geng::MouseButton::Left) {
+                            self.trajectory.start = Some(cursor);
+                            self.trajectory_stale = true;
+                        }
+                    }
+                    Tool::Select => self.select_drag(cursor),
+                    Tool::Vertex => self.vertex_move(cursor),
+                },
                 geng::Event::MousePress {
                     button: geng::MouseButton::Right,
                 } => {
-                    let cursor = self.screen_to_world(
-                        self.geng.window().cursor_position().unwrap_or(vec2::ZERO),
-                    );
+                    if self.tool == Tool::Vertex && self.dissolve_vertex(cursor) {
+                        return;
+                    }
                     if let Some(index) = self.hovered_surface(cursor) {
-                        self.level.surfaces.remove(index);
-                        self.save_level();
-                        self.update_level();
+                        self.selection.clear();
+                        self.edit(history::Command::remove_surfaces(&self.level, vec![index]));
                     }
                 }
+                geng::Event::MouseRelease {
+                    button: geng::MouseButton::Left,
+                } if self.tool == Tool::Select => self.select_release(cursor),
+                geng::Event::MouseRelease {
+                    button: geng::MouseButton::Left,
+                } if self.tool == Tool::Vertex => self.vertex_release(),
                 geng::Event::MouseRelease { .. } => {
                     if let Some(start) = self.start_draw.take() {
                         if let Some(end) = self
@@ -1024,24 +2731,64 @@ impl geng::State for Game {
                             .cursor_position()
                             .map(|pos| self.snapped(pos))
                         {
-                            self.level.surfaces.push(Surface { ends: [start, end] });
-                            self.save_level();
-                            self.update_level();
+                            self.edit(history::Command::AddSurfaces(vec![Surface {
+                                ends: [start, end],
+                            }]));
                         }
                     }
                 }
+                geng::Event::KeyPress { key: geng::Key::Z } if ctrl => {
+                    if shift {
+                        self.redo();
+                    } else {
+                        self.undo();
+                    }
+                }
+                geng::Event::KeyPress { key: geng::Key::S } if ctrl => {
+                    self.save_level();
+                }
+                geng::Event::KeyPress { key: geng::Key::D } if ctrl => self.duplicate_selection(),
+                geng::Event::KeyPress { key: geng::Key::C } if ctrl => {
+                    self.copy_selection();
+                }
+                geng::Event::KeyPress { key: geng::Key::X } if ctrl => self.cut_selection(),
+                geng::Event::KeyPress { key: geng::Key::V } if ctrl => {
+                    let pos = self.snapped(window.cursor_position().unwrap_or(vec2::ZERO));
+                    self.paste(pos);
+                }
+                geng::Event::KeyPress {
+                    key: geng::Key::Enter,
+                } if self.tool == Tool::Polyline => self.finish_polyline(),
+                geng::Event::KeyPress {
+                    key: geng::Key::Backspace,
+                } if self.tool == Tool::Polyline => {
+                    self.polyline.pop();
+                }
                 geng::Event::KeyPress { key } => match key {
+                    geng::Key::Digit1 => self.tool = Tool::Draw,
+                    geng::Key::Digit2 => self.tool = Tool::Select,
+                    geng::Key::Digit3 => self.tool = Tool::Vertex,
+                    geng::Key::Digit4 => self.tool = Tool::Polyline,
+                    geng::Key::Digit5 => self.tool = Tool::Trajectory,
+                    geng::Key::Delete | geng::Key::Backspace => self.delete_selection(),
+                    geng::Key::P => {
+                        self.selection.custom_pivot = if shift { None } else { Some(cursor) };
+                    }
                     geng::Key::Z => {
-                        self.level.start_pos = self.screen_to_world(
-                            self.geng.window().cursor_position().unwrap_or(vec2::ZERO),
-                        );
-                        self.save_level();
+                        self.edit(history::Command::SetStart {
+                            before: self.level.start_pos,
+                            after: self.snapped(
+                                self.geng.window().cursor_position().unwrap_or(vec2::ZERO),
+                            ),
+                        });
                     }
                     geng::Key::X => {
-                        self.level.finish_pos = self.screen_to_world(
-                            self.geng.window().cursor_position().unwrap_or(vec2::ZERO),
-                        );
-                        self.save_level();
+                        self.edit(history::Command::SetFinish {
+                            before: self.level.finish_pos,
+                            after: self.snapped(
+                                self.geng.window().cursor_position().unwrap_or(vec2::ZERO),
+                            ),
+                        });
                     }
                     _ => {}
                 },
@@ -1055,12 +2802,218 @@ impl geng::State for Game {
 struct CliArgs {
     #[clap(long)]
     enable_editor: bool,
+    /// Additional level pack directory, can be repeated
+    #[clap(long = "levels")]
+    levels: Vec<std::path::PathBuf>,
+    #[clap(subcommand)]
+    command: Option<Command>,
     #[clap(flatten)]
     geng: geng::CliArgs,
 }
 
+/// Runs the game when no command is given
+#[derive(clap::Subcommand)]
+enum Command {
+    /// Check level files, or all level packs if none given, exits with 1 on issues
+    Validate { paths: Vec<std::path::PathBuf> },
+    /// Print surface count, bounds and total surface length
+    Stats { path: std::path::PathBuf },
+    /// Convert between level formats detected by extension: json, svg, txt (share code)
+    #[clap(alias = "import-svg", alias = "export-svg")]
+    Convert {
+        input: std::path::PathBuf,
+        output: std::path::PathBuf,
+        /// How many svg pixels make one world unit
+        #[clap(long, default_value = "100")]
+        pixels_per_unit: f32,
+        /// Max distance between svg curves and their flattened segments, in world units
+        #[clap(long, default_value = "0.02")]
+        tolerance: f32,
+    },
+    /// Round coordinates, merge close endpoints and remove zero length or duplicate surfaces
+    Normalize {
+        path: std::path::PathBuf,
+        /// Where to write the level, overwrites the input if omitted
+        #[clap(long)]
+        output: Option<std::path::PathBuf>,
+        #[clap(long, default_value = "0.001")]
+        precision: f32,
+        /// Endpoints closer than this are merged, defaults to the precision
+        #[clap(long)]
+        merge_distance: Option<f32>,
+    },
+    /// Print the differences between two levels
+    Diff {
+        a: std::path::PathBuf,
+        b: std::path::PathBuf,
+        /// Points closer than this are considered equal
+        #[clap(long, default_value = "0.001")]
+        tolerance: f32,
+    },
+    /// Run a replay without a window and print the official time of each level
+    VerifyReplay { path: std::path::PathBuf },
+    /// Generate a random level that is checked to be solvable
+    Generate {
+        output: std::path::PathBuf,
+        /// Seed of the first attempt, the next seeds are tried if the level is not solvable
+        #[clap(long, default_value = "0")]
+        seed: u64,
+        /// From 0 to 1
+        #[clap(long, default_value = "0.5")]
+        difficulty: f32,
+        #[clap(long, default_value = "6")]
+        sections: usize,
+        #[clap(long, default_value = "20")]
+        attempts: usize,
+    },
+}
+
+/// Exits with 1 on errors and when the command finds issues
+fn run_command(command: &Command, levels: &[std::path::PathBuf]) -> anyhow::Result<()> {
+    let load_config = || -> anyhow::Result<Config> {
+        futures::executor::block_on(file::load_detect::<Config>(config_path()))
+            .with_context(|| format!("Failed to load {:?}", config_path()))
+    };
+    let load = |path: &std::path::Path, options: &svg::Options| {
+        tools::load(path, options).with_context(|| format!("Failed to load {path:?}"))
+    };
+    let save = |path: &std::path::Path, level: &Level, config: &Config, options: &svg::Options| {
+        tools::save(path, level, config, options)
+            .with_context(|| format!("Failed to save {path:?}"))
+    };
+    match command {
+        Command::Validate { paths } => {
+            let config = load_config()?;
+            let mut valid = true;
+            if paths.is_empty() {
+                for pack in futures::executor::block_on(pack::load_all(levels)) {
+                    println!("pack {}:", pack.name);
+                    valid &= validate::validate_all(&config, &pack.levels);
+                }
+            }
+            for path in paths {
+                let issues = validate::validate(&config, &load(path, &default())?);
+                for issue in &issues {
+                    println!("{}: {issue}", path.display());
+                }
+                valid &= issues.is_empty();
+            }
+            if !valid {
+                std::process::exit(1);
+            }
+        }
+        Command::VerifyReplay { path } => {
+            let config = load_config()?;
+            let replay =
+                replay::Replay::load(path).with_context(|| format!("Failed to load {path:?}"))?;
+            let packs = futures::executor::block_on(pack::load_all(levels));
+            let Some(pack) = packs.iter().find(|pack| pack.name == replay.pack) else {
+                anyhow::bail!("Level pack {:?} not found", replay.pack);
+            };
+            for run in &replay.levels {
+                let Some(level) = pack.levels.map.get(&run.level) else {
+                    anyhow::bail!("Level {:?} not found", run.level);
+                };
+                match replay::verify(&config, level, run) {
+                    Ok(()) => println!(
+                        "{}: {} ({} ticks)",
+                        run.level,
+                        format_time(physics::ticks_to_seconds(run.ticks)),
+                        run.ticks,
+                    ),
+                    Err(e) => {
+                        println!("{}: {e}", run.level);
+                        std::process::exit(1);
+                    }
+                }
+            }
+            let total = replay.total_ticks();
+            println!(
+                "total: {} ({total} ticks)",
+                format_time(physics::ticks_to_seconds(total)),
+            );
+        }
+        Command::Stats { path } => {
+            let level = load(path, &default())?;
+            println!("{}", tools::Stats::new(&level));
+        }
+        Command::Convert {
+            input,
+            output,
+            pixels_per_unit,
+            tolerance,
+        } => {
+            let options = svg::Options {
+                pixels_per_unit: *pixels_per_unit,
+                tolerance: *tolerance,
+            };
+            let level = load(input, &options)?;
+            save(output, &level, &load_config()?, &options)?;
+        }
+        Command::Normalize {
+            path,
+            output,
+            precision,
+            merge_distance,
+        } => {
+            if *precision <= 0.0 {
+                anyhow::bail!("Precision must be positive");
+            }
+            let merge_distance = merge_distance.unwrap_or(*precision);
+            if merge_distance < 0.0 {
+                anyhow::bail!("Merge distance must not be negative");
+            }
+            let mut level = load(path, &default())?;
+            println!(
+                "{}",
+                tools::normalize(&mut level, *precision, merge_distance),
+            );
+            let output = output.as_ref().unwrap_or(path);
+            save(output, &level, &load_config()?, &default())?;
+        }
+        Command::Diff { a, b, tolerance } => {
+            let changes = tools::diff(&load(a, &default())?, &load(b, &default())?, *tolerance);
+            for change in &changes {
+                println!("{change}");
+            }
+            if !changes.is_empty() {
+                std::process::exit(1);
+            }
+        }
+        Command::Generate {
+            output,
+            seed,
+            difficulty,
+            sections,
+            attempts,
+        } => {
+            let config = load_config()?;
+            let options = generator::Options {
+                seed: *seed,
+                difficulty: *difficulty,
+                sections: *sections,
+                attempts: *attempts,
+                ..default()
+            };
+            let (seed, level) =
+                generator::generate(&config, &options).context("Failed to generate a level")?;
+            println!("seed {seed}");
+            migrations::save_file(output, &level)
+                .with_context(|| format!("Failed to save {output:?}"))?;
+        }
+    }
+    Ok(())
+}
+
 fn main() {
     let cli: CliArgs = cli::parse();
+    if let Some(command) = &cli.command {
+        if let Err(e) = run_command(command, &cli.levels) {
+            eprintln!("{e:#}");
+            std::process::exit(1);
+        }
+        return;
+    }
     Geng::run_with(
         &{
             let mut options = geng::ContextOptions::default();
@@ -0,0 +1,122 @@
+use super::*;
+
+use serde_json::Value;
+
+/// Version written by `save_level`, bump it when adding a migration
+pub const CURRENT_VERSION: u64 = 1;
+
+/// `MIGRATIONS[i]` upgrades a level from version `i` to version `i + 1`
+const MIGRATIONS: [fn(&mut serde_json::Map<String, Value>); CURRENT_VERSION as usize] = [v0_to_v1];
+
+/// Files saved before versioning relied on serde defaults for missing fields
+fn v0_to_v1(json: &mut serde_json::Map<String, Value>) {
+    json.entry("start_pos")
+        .or_insert_with(|| serde_json::json!([0.0, 0.0]));
+    json.entry("finish_pos")
+        .or_insert_with(|| serde_json::json!([5.0, 0.0]));
+    json.entry("surfaces")
+        .or_insert_with(|| serde_json::json!([]));
+}
+
+pub fn upgrade(json: Value) -> anyhow::Result<Level> {
+    let Value::Object(mut json) = json else {
+        anyhow::bail!("level must be a json object");
+    };
+    let version = match json.remove("version") {
+        None => 0,
+        Some(version) => version
+            .as_u64()
+            .ok_or_else(|| anyhow::anyhow!("level version must be a non-negative integer"))?,
+    };
+    if version > CURRENT_VERSION {
+        anyhow::bail!("level version {version} is newer than supported {CURRENT_VERSION}");
+    }
+    for migration in &MIGRATIONS[version as usize..] {
+        migration(&mut json);
+    }
+    Ok(serde_json::from_value(Value::Object(json))?)
+}
+
+pub fn versioned(level: &Level) -> Value {
+    let mut json = serde_json::to_value(level).unwrap();
+    json.as_object_mut()
+        .unwrap()
+        .insert("version".to_owned(), CURRENT_VERSION.into());
+    json
+}
+
+pub fn load_file(path: &std::path::Path) -> anyhow::Result<Level> {
+    let json = serde_json::from_reader(std::io::BufReader::new(std::fs::File::open(path)?))?;
+    upgrade(json)
+}
+
+pub fn save_file(path: &std::path::Path, level: &Level) -> anyhow::Result<()> {
+    files::write_atomic(path, &serde_json::to_vec_pretty(&versioned(level))?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(json: &str) -> anyhow::Result<Level> {
+        upgrade(serde_json::from_str(json)?)
+    }
+
+    fn levels_dir() -> std::path::PathBuf {
+        std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("assets")
+            .join("levels")
+    }
+
+    #[test]
+    fn shipped_levels_upgrade() {
+        let manifest: campaign::Manifest = serde_json::from_str(
+            &std::fs::read_to_string(levels_dir().join(campaign::Manifest::FILE_NAME)).unwrap(),
+        )
+        .unwrap();
+        for name in manifest
+            .campaigns
+            .into_iter()
+            .flat_map(|campaign| campaign.levels)
+            .map(|level| level.name)
+        {
+            let path = levels_dir().join(&name).with_extension("json");
+            let level = parse(&std::fs::read_to_string(path).unwrap())
+                .unwrap_or_else(|e| panic!("failed to load {name}: {e}"));
+            let resaved = upgrade(versioned(&level)).unwrap();
+            assert_eq!(
+                serde_json::to_value(&level).unwrap(),
+                serde_json::to_value(&resaved).unwrap(),
+                "{name} changed after resaving",
+            );
+        }
+    }
+
+    #[test]
+    fn unversioned_defaults() {
+        let level = parse("{}").unwrap();
+        assert_eq!(level.start_pos, vec2::ZERO);
+        assert_eq!(level.finish_pos, vec2(5.0, 0.0));
+        assert!(level.surfaces.is_empty());
+    }
+
+    #[test]
+    fn missing_finish_radius_uses_config() {
+        let level =
+            parse(r#"{"version": 1, "start_pos": [0, 0], "finish_pos": [5, 0], "surfaces": []}"#)
+                .unwrap();
+        assert_eq!(level.finish_radius, None);
+        let level = upgrade(versioned(&Level {
+            finish_radius: Some(0.5),
+            ..level
+        }))
+        .unwrap();
+        assert_eq!(level.finish_radius, Some(0.5));
+    }
+
+    #[test]
+    fn newer_version_rejected() {
+        let json = format!(r#"{{"version": {}}}"#, CURRENT_VERSION + 1);
+        assert!(parse(&json).is_err());
+    }
+}
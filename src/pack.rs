@@ -0,0 +1,366 @@
+use super::*;
+
+/// Directory of levels with its own manifest
+pub struct Pack {
+    pub name: String,
+    pub dir: std::path::PathBuf,
+    /// Shipped assets are copied into the user directory before the first edit
+    pub read_only: bool,
+    pub levels: Levels,
+}
+
+/// Where user level packs live, one per subdirectory
+pub fn user_dir() -> Option<std::path::PathBuf> {
+    files::data_dir().map(|dir| dir.join("levels"))
+}
+
+const SHIPPED_NAME: &str = "main";
+
+fn shipped_dir() -> std::path::PathBuf {
+    run_dir().join("assets").join("levels")
+}
+
+fn same_dir(a: &std::path::Path, b: &std::path::Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+fn dir_name(dir: &std::path::Path) -> String {
+    dir.file_name().map_or_else(
+        || dir.display().to_string(),
+        |name| name.to_string_lossy().into_owned(),
+    )
+}
+
+/// Previous versions of levels, inside the pack directory
+const BACKUP_DIR: &str = "_backups";
+
+/// Shipped pack first, then user packs, then packs given with `--levels`.
+/// A user copy of the shipped pack is loaded in its place
+pub async fn load_all(extra: &[std::path::PathBuf]) -> Vec<Pack> {
+    let shipped = shipped_dir();
+    let mut sources = vec![(
+        SHIPPED_NAME.to_owned(),
+        shipped.clone(),
+        // Passing the shipped directory explicitly allows editing it in place
+        !extra.iter().any(|dir| same_dir(dir, &shipped)),
+    )];
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Some(user_dir) = user_dir() {
+        let mut dirs: Vec<_> = std::fs::read_dir(user_dir)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .map(|entry| entry.path())
+            // Hidden ones are unfinished copies, see `make_writable`
+            .filter(|dir| dir.is_dir() && !dir_name(dir).starts_with('.'))
+            .collect();
+        dirs.sort();
+        for dir in dirs {
+            let name = dir_name(&dir);
+            if name == SHIPPED_NAME && sources[0].2 {
+                sources[0] = (name, dir, false);
+            } else {
+                sources.push((name, dir, false));
+            }
+        }
+    }
+    sources.extend(
+        extra
+            .iter()
+            .filter(|dir| !same_dir(dir, &shipped))
+            .map(|dir| (dir_name(dir), dir.clone(), false)),
+    );
+
+    let mut packs: Vec<Pack> = Vec::new();
+    for (name, dir, read_only) in sources {
+        // Progress is stored by pack name
+        if packs.iter().any(|pack| pack.name == name) {
+            log::error!("Skipping level pack {dir:?}, there is another pack named {name:?}");
+            continue;
+        }
+        match Levels::load_dir(&dir).await {
+            Ok(levels) => packs.push(Pack {
+                name,
+                dir,
+                read_only,
+                levels,
+            }),
+            Err(e) => log::error!("Failed to load level pack {dir:?}: {e}"),
+        }
+    }
+    packs
+}
+
+impl Pack {
+    fn level_path(&self, name: &str) -> std::path::PathBuf {
+        self.dir.join(name).with_extension("json")
+    }
+
+    pub fn save_manifest(&self) -> anyhow::Result<()> {
+        files::write_atomic(
+            &self.dir.join(campaign::Manifest::FILE_NAME),
+            &serde_json::to_vec_pretty(&self.levels.manifest)?,
+        )
+    }
+
+    /// Saves the manifest and only then replaces the one in memory
+    fn commit_manifest(&mut self, manifest: campaign::Manifest) -> anyhow::Result<()> {
+        manifest.validate()?;
+        let old = std::mem::replace(&mut self.levels.manifest, manifest);
+        if let Err(e) = self.save_manifest() {
+            self.levels.manifest = old;
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    fn check_new_name(&self, name: &str) -> anyhow::Result<()> {
+        if name.is_empty()
+            || name.starts_with('_')
+            || name.contains(|c: char| std::path::is_separator(c) || c == '.')
+        {
+            anyhow::bail!("invalid level name {name:?}");
+        }
+        if self.levels.map.contains_key(name) {
+            anyhow::bail!("level {name:?} already exists");
+        }
+        Ok(())
+    }
+
+    fn is_listed(manifest: &campaign::Manifest, name: &str) -> bool {
+        manifest
+            .campaigns
+            .iter()
+            .any(|campaign| campaign.levels.iter().any(|level| level.name == name))
+    }
+
+    /// Inserts the level into the campaign at `index`
+    pub fn create_level(
+        &mut self,
+        campaign: usize,
+        index: usize,
+        info: campaign::LevelInfo,
+        level: Level,
+    ) -> anyhow::Result<()> {
+        self.check_new_name(&info.name)?;
+        if self.read_only {
+            self.make_writable()?;
+        }
+        let path = self.level_path(&info.name);
+        migrations::save_file(&path, &level)?;
+        let name = info.name.clone();
+        let mut manifest = self.levels.manifest.clone();
+        manifest.campaigns[campaign].levels.insert(index, info);
+        if let Err(e) = self.commit_manifest(manifest) {
+            let _ = std::fs::remove_file(&path);
+            return Err(e);
+        }
+        self.levels.map.insert(name, level);
+        Ok(())
+    }
+
+    /// Renames the file and every reference in the manifest
+    pub fn rename_level(&mut self, old: &str, new: &str) -> anyhow::Result<()> {
+        self.check_new_name(new)?;
+        if self.read_only {
+            self.make_writable()?;
+        }
+        let mut manifest = self.levels.manifest.clone();
+        for campaign in &mut manifest.campaigns {
+            for level in &mut campaign.levels {
+                if level.name == old {
+                    level.name = new.to_owned();
+                }
+                if let campaign::Unlock::Levels(names) = &mut level.unlock {
+                    for name in names.iter_mut().filter(|name| *name == old) {
+                        *name = new.to_owned();
+                    }
+                }
+            }
+        }
+        std::fs::rename(self.level_path(old), self.level_path(new))?;
+        if let Err(e) = self.commit_manifest(manifest) {
+            let _ = std::fs::rename(self.level_path(new), self.level_path(old));
+            return Err(e);
+        }
+        if let Some(level) = self.levels.map.remove(old) {
+            self.levels.map.insert(new.to_owned(), level);
+        }
+        Ok(())
+    }
+
+    /// Removes the level from the campaign, the file is deleted once nothing lists it
+    pub fn delete_level(&mut self, campaign: usize, index: usize) -> anyhow::Result<()> {
+        if self.levels.manifest.campaigns[campaign].levels.len() <= 1 {
+            anyhow::bail!("a campaign needs at least one level");
+        }
+        if self.read_only {
+            self.make_writable()?;
+        }
+        let mut manifest = self.levels.manifest.clone();
+        let name = manifest.campaigns[campaign].levels.remove(index).name;
+        let listed = Self::is_listed(&manifest, &name);
+        if !listed {
+            for level in manifest
+                .campaigns
+                .iter_mut()
+                .flat_map(|campaign| &mut campaign.levels)
+            {
+                if let campaign::Unlock::Levels(names) = &mut level.unlock {
+                    names.retain(|required| *required != name);
+                }
+            }
+        }
+        self.commit_manifest(manifest)?;
+        if !listed {
+            self.levels.map.remove(&name);
+            if let Err(e) = std::fs::remove_file(self.level_path(&name)) {
+                log::error!("Failed to delete level file of {name:?}: {e}");
+            }
+        }
+        Ok(())
+    }
+
+    pub fn move_level(&mut self, campaign: usize, from: usize, to: usize) -> anyhow::Result<()> {
+        if self.read_only {
+            self.make_writable()?;
+        }
+        let mut manifest = self.levels.manifest.clone();
+        let levels = &mut manifest.campaigns[campaign].levels;
+        let level = levels.remove(from);
+        levels.insert(to, level);
+        self.commit_manifest(manifest)
+    }
+
+    /// Copies the whole pack into the user directory so that edits don't touch shipped assets.
+    /// The copy keeps the name so that progress stays with it,
+    /// and is loaded in place of the shipped pack from then on
+    fn make_writable(&mut self) -> anyhow::Result<()> {
+        let user_dir = user_dir().ok_or_else(|| anyhow::anyhow!("no user data directory"))?;
+        let dir = user_dir.join(&self.name);
+        if dir.exists() {
+            anyhow::bail!("{dir:?} already exists but could not be loaded");
+        }
+        // Copied into a hidden directory first, so a failed copy is never loaded as the pack
+        let temp = user_dir.join(format!(".{}", self.name));
+        if temp.exists() {
+            std::fs::remove_dir_all(&temp)?;
+        }
+        std::fs::create_dir_all(&temp)?;
+        let result = self
+            .copy_into(&temp)
+            .and_then(|()| Ok(std::fs::rename(&temp, &dir)?));
+        if let Err(e) = result {
+            if let Err(e) = std::fs::remove_dir_all(&temp) {
+                log::error!("Failed to remove the partial copy {temp:?}: {e}");
+            }
+            return Err(e);
+        }
+        self.dir = dir;
+        self.read_only = false;
+        log::info!("Copied level pack into {:?}", self.dir);
+        Ok(())
+    }
+
+    /// Writes the manifest, levels and music tracks into an empty directory
+    fn copy_into(&self, dir: &std::path::Path) -> anyhow::Result<()> {
+        files::write_atomic(
+            &dir.join(campaign::Manifest::FILE_NAME),
+            &serde_json::to_vec_pretty(&self.levels.manifest)?,
+        )?;
+        for (level_name, level) in &self.levels.map {
+            migrations::save_file(&dir.join(level_name).with_extension("json"), level)?;
+        }
+        for track in self.levels.manifest.music_tracks() {
+            let path = std::path::Path::new(track);
+            if !path
+                .components()
+                .all(|component| matches!(component, std::path::Component::Normal(_)))
+            {
+                log::warn!("Not copying music {track:?} from outside of the pack");
+                continue;
+            }
+            let target = dir.join(path);
+            if let Some(parent) = target.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::copy(self.dir.join(path), target)?;
+        }
+        Ok(())
+    }
+
+    /// Used when no pack could be loaded, so that the editor still opens
+    pub fn fallback() -> Self {
+        let name = "level".to_owned();
+        let mut map = HashMap::new();
+        map.insert(
+            name.clone(),
+            Level {
+                start_pos: vec2::ZERO,
+                finish_pos: vec2(5.0, 0.0),
+                surfaces: Vec::new(),
+                finish_radius: None,
+            },
+        );
+        Self {
+            name: "new".to_owned(),
+            dir: shipped_dir(),
+            read_only: true,
+            levels: Levels {
+                manifest: campaign::Manifest {
+                    campaigns: vec![campaign::Campaign {
+                        name: "main".to_owned(),
+                        title: None,
+                        requires: Vec::new(),
+                        levels: vec![campaign::LevelInfo::new(name)],
+                    }],
+                },
+                map,
+            },
+        }
+    }
+
+    fn backup_dir(&self, name: &str) -> std::path::PathBuf {
+        self.dir.join(BACKUP_DIR).join(name)
+    }
+
+    /// Copies the level file into its backup directory, keeping only the newest `count` copies
+    fn backup_level(&self, name: &str, count: usize) -> anyhow::Result<()> {
+        let path = self.level_path(name);
+        if count == 0 || !path.exists() {
+            return Ok(());
+        }
+        let dir = self.backup_dir(name);
+        std::fs::create_dir_all(&dir)?;
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)?
+            .as_millis();
+        // Zero padded so that the names sort by time
+        std::fs::copy(&path, dir.join(format!("{timestamp:016}.json")))?;
+        let mut backups: Vec<_> = std::fs::read_dir(&dir)?
+            .flatten()
+            .map(|entry| entry.path())
+            .collect();
+        backups.sort();
+        for old in &backups[..backups.len().saturating_sub(count)] {
+            std::fs::remove_file(old)?;
+        }
+        Ok(())
+    }
+
+    /// Backs up the previous version of the file before replacing it
+    pub fn save_level(&mut self, name: &str, level: &Level, backups: usize) -> anyhow::Result<()> {
+        if self.read_only {
+            self.make_writable()?;
+        }
+        if let Err(e) = self.backup_level(name, backups) {
+            log::error!("Failed to back up level {name:?}: {e}");
+        }
+        migrations::save_file(&self.level_path(name), level)?;
+        self.levels.map.insert(name.to_owned(), level.clone());
+        Ok(())
+    }
+}
@@ -0,0 +1,136 @@
+use super::*;
+
+pub const TICKS_PER_SECOND: u64 = 200;
+pub const TICK: f32 = 1.0 / TICKS_PER_SECOND as f32;
+
+pub fn ticks_to_seconds(ticks: u64) -> f32 {
+    ticks as f32 / TICKS_PER_SECOND as f32
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Scaling {
+    None,
+    /// Hold LMB
+    Grow,
+    /// Hold RMB
+    Shrink,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Input {
+    pub scaling: Scaling,
+    /// World position of the cursor, the player scales around the closest point to it
+    pub cursor: vec2<f32>,
+}
+
+pub struct TickEvents {
+    pub scaling_speed: f32,
+    /// Normal velocities of collisions, positive
+    pub hits: Vec<f32>,
+    pub finished: bool,
+}
+
+impl Player {
+    pub fn new(config: &Config, pos: vec2<f32>) -> Self {
+        Self {
+            pos,
+            vel: vec2::ZERO,
+            radius: config.player.radius,
+            r#static: 0.0,
+            scale_origin: vec2::ZERO,
+        }
+    }
+
+    pub fn tick(
+        &mut self,
+        config: &Config,
+        level: &Level,
+        input: &Input,
+        delta_time: f32,
+    ) -> TickEvents {
+        let player = self;
+        player.r#static = (player.r#static + delta_time / config.r#static.time_to_full).min(1.0);
+        if player.vel.len() > config.r#static.max_vel {
+            player.r#static = 0.0;
+        }
+        player.vel.y -= config.gravity * delta_time * (1.0 - player.r#static);
+        player.pos += player.vel * delta_time * (1.0 - player.r#static);
+
+        let target_radius = match input.scaling {
+            Scaling::Grow => config.player.max_radius,
+            Scaling::Shrink => config.player.min_radius,
+            Scaling::None => config.player.radius,
+        };
+        let scaling_speed = (target_radius - player.radius) * config.player.scaling_speed;
+        if scaling_speed.abs() > config.r#static.max_vel {
+            player.r#static = 0.0;
+        }
+        let old_radius = player.radius;
+        let scale_origin = player.pos + (input.cursor - player.pos).clamp_len(..=player.radius);
+        player.scale_origin = scale_origin;
+        let new_radius = (player.radius + scaling_speed * delta_time)
+            .clamp(config.player.min_radius, config.player.max_radius);
+        player.pos = scale_origin + (player.pos - scale_origin) * new_radius / old_radius;
+        player.radius = new_radius;
+
+        let mut hits = Vec::new();
+        for surface in &level.surfaces {
+            let to = surface.to(player.pos);
+            if to.distance < player.radius {
+                let penetration = player.radius - to.distance;
+                player.pos += to.normal * penetration;
+                player.radius -= penetration;
+                let vel_at_collision_point =
+                    player.vel + scaling_speed * (to.closest_point - scale_origin) / old_radius;
+                let normal_vel = vec2::dot(vel_at_collision_point, to.normal);
+                if normal_vel < 0.0 {
+                    player.vel -= to.normal * normal_vel * (1.0 + config.bounciness);
+                    hits.push(-normal_vel);
+                }
+                let along = to.normal.rotate_90();
+                let along_vel = vec2::dot(vel_at_collision_point, along);
+                player.vel -= along * along_vel.clamp_abs(normal_vel.abs() * config.friction);
+            }
+        }
+
+        TickEvents {
+            scaling_speed,
+            hits,
+            finished: (player.pos - level.finish_pos).len()
+                < player.radius + level.finish_radius(config),
+        }
+    }
+
+    /// Length of the next tick, short enough to not tunnel through surfaces
+    pub fn max_tick(&self, config: &Config) -> f32 {
+        config.tick_distance / self.vel.len().max(1.0)
+    }
+
+    /// Advances by one fixed tick, split into substeps short enough to not tunnel.
+    /// Official times are counted in these ticks so that replays are reproducible.
+    pub fn step(&mut self, config: &Config, level: &Level, input: &Input) -> TickEvents {
+        let mut events = TickEvents {
+            scaling_speed: 0.0,
+            hits: Vec::new(),
+            finished: false,
+        };
+        let mut left = TICK;
+        while left > 0.0 {
+            let delta_time = left.min(self.max_tick(config));
+            let tick = self.tick(config, level, input, delta_time);
+            events.scaling_speed = tick.scaling_speed;
+            events.hits.extend(tick.hits);
+            if tick.finished {
+                events.finished = true;
+                break;
+            }
+            left -= delta_time;
+        }
+        events
+    }
+
+    /// Runs `ticks` steps with the same input, returns whether the finish was reached
+    pub fn simulate(&mut self, config: &Config, level: &Level, input: &Input, ticks: u64) -> bool {
+        (0..ticks).any(|_| self.step(config, level, input).finished)
+    }
+}
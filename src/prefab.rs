@@ -0,0 +1,141 @@
+use super::*;
+
+/// Saved fragment, the name is the file name
+pub struct Prefab {
+    pub name: String,
+    pub fragment: clipboard::Fragment,
+}
+
+fn shipped_dir() -> std::path::PathBuf {
+    run_dir().join("assets").join("prefabs")
+}
+
+/// Where saved prefabs go, next to the user level packs
+pub fn user_dir() -> Option<std::path::PathBuf> {
+    files::data_dir().map(|dir| dir.join("prefabs"))
+}
+
+fn search_dirs() -> impl Iterator<Item = std::path::PathBuf> {
+    std::iter::once(shipped_dir()).chain(user_dir())
+}
+
+fn exists(name: &str) -> bool {
+    search_dirs().any(|dir| dir.join(name).with_extension("json").exists())
+}
+
+/// Shipped and user prefabs sorted by name, files that fail to load
+/// and names already taken by a shipped prefab are skipped
+pub fn load_all() -> Vec<Prefab> {
+    let mut prefabs = Vec::<Prefab>::new();
+    for path in search_dirs().flat_map(|dir| {
+        let mut paths: Vec<_> = std::fs::read_dir(dir)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().map_or(false, |ext| ext == "json"))
+            .collect();
+        paths.sort();
+        paths
+    }) {
+        let Some(name) = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+        else {
+            continue;
+        };
+        if prefabs.iter().any(|prefab| prefab.name == name) {
+            log::error!("Skipping prefab {path:?}, the name {name:?} is already taken");
+            continue;
+        }
+        let result = std::fs::read_to_string(&path)
+            .map_err(anyhow::Error::from)
+            .and_then(|json| Ok(serde_json::from_str(&json)?));
+        match result {
+            Ok(fragment) => prefabs.push(Prefab { name, fragment }),
+            Err(e) => log::error!("Failed to load prefab {path:?}: {e}"),
+        }
+    }
+    prefabs.sort_by(|a, b| a.name.cmp(&b.name));
+    prefabs
+}
+
+/// Never overwrites, a taken name gets a number appended.
+/// Returns the name it was saved under
+pub fn save(name: &str, fragment: &clipboard::Fragment) -> anyhow::Result<String> {
+    if name.is_empty() || name.contains(['/', '\\', '.']) {
+        anyhow::bail!("invalid prefab name {name:?}");
+    }
+    let dir = user_dir().ok_or_else(|| anyhow::anyhow!("no user data directory"))?;
+    let name = std::iter::once(name.to_owned())
+        .chain((2..).map(|n| format!("{name}-{n}")))
+        .find(|name| !exists(name))
+        .unwrap();
+    std::fs::create_dir_all(&dir)?;
+    files::write_atomic(
+        &dir.join(&name).with_extension("json"),
+        &serde_json::to_vec_pretty(fragment)?,
+    )?;
+    Ok(name)
+}
+
+/// Prefab following the cursor until placed
+pub struct Placement {
+    pub fragment: clipboard::Fragment,
+    /// In degrees
+    pub rotation: f32,
+    /// Flipped horizontally before rotating
+    pub mirrored: bool,
+}
+
+impl Placement {
+    pub fn new(fragment: clipboard::Fragment) -> Self {
+        Self {
+            fragment,
+            rotation: 0.0,
+            mirrored: false,
+        }
+    }
+
+    pub fn transformed(&self) -> clipboard::Fragment {
+        let rotation = Angle::from_degrees(self.rotation);
+        let mirror = if self.mirrored { -1.0 } else { 1.0 };
+        self.fragment
+            .map(|p| vec2(p.x * mirror, p.y).rotate(rotation))
+    }
+}
+
+/// Surfaces scaled to fit the button, clicking it returns true
+pub fn thumbnail(ui: &mut egui::Ui, fragment: &clipboard::Fragment, size: f32) -> bool {
+    let (rect, response) = ui.allocate_exact_size(egui::vec2(size, size), egui::Sense::click());
+    let painter = ui.painter_at(rect);
+    let background = if response.hovered() {
+        ui.visuals().widgets.hovered.bg_fill
+    } else {
+        ui.visuals().widgets.inactive.bg_fill
+    };
+    painter.rect_filled(rect, 2.0, background);
+    let points = fragment
+        .surfaces
+        .iter()
+        .flat_map(|surface| surface.ends)
+        .chain(fragment.start_pos)
+        .chain(fragment.finish_pos);
+    let extent = points
+        .map(|p| p.x.abs().max(p.y.abs()))
+        .fold(0.0, f32::max)
+        .max(1e-3);
+    let scale = size * 0.45 / extent;
+    let to_screen = |p: vec2<f32>| rect.center() + egui::vec2(p.x, -p.y) * scale;
+    let stroke = egui::Stroke::new(1.0, ui.visuals().text_color());
+    for surface in &fragment.surfaces {
+        painter.line_segment(surface.ends.map(to_screen), stroke);
+    }
+    if let Some(pos) = fragment.start_pos {
+        painter.circle_filled(to_screen(pos), 2.0, egui::Color32::GREEN);
+    }
+    if let Some(pos) = fragment.finish_pos {
+        painter.circle_filled(to_screen(pos), 2.0, egui::Color32::RED);
+    }
+    response.clicked()
+}
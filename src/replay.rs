@@ -0,0 +1,260 @@
+use super::*;
+
+/// Player position is recorded this often to find where a replay diverged
+pub const CHECKPOINT_INTERVAL: u64 = 100;
+/// Checkpoints further than this from the simulated position mean divergence
+const CHECKPOINT_TOLERANCE: f32 = 1e-3;
+
+/// Inputs of a single level attempt that reached the finish
+#[derive(Serialize, Deserialize, Clone)]
+pub struct LevelRun {
+    pub level: String,
+    /// Official time, the finish is reached on the last tick
+    pub ticks: u64,
+    /// Input changes, each one applies from its tick onwards
+    pub inputs: Vec<(u64, physics::Input)>,
+    pub checkpoints: Vec<vec2<f32>>,
+    /// Of the level and physics config the run was recorded with
+    pub fingerprint: u32,
+}
+
+impl LevelRun {
+    pub fn new(level_name: &str, config: &Config, level: &Level) -> Self {
+        Self {
+            level: level_name.to_owned(),
+            ticks: 0,
+            inputs: Vec::new(),
+            checkpoints: Vec::new(),
+            fingerprint: fingerprint(config, level),
+        }
+    }
+
+    /// Call with the input used for the next step and the player after the step
+    pub fn record(&mut self, input: &physics::Input, player: &Player) {
+        if self.inputs.last().map(|(_, last)| last) != Some(input) {
+            self.inputs.push((self.ticks, *input));
+        }
+        self.ticks += 1;
+        if self.ticks % CHECKPOINT_INTERVAL == 0 {
+            self.checkpoints.push(player.pos);
+        }
+    }
+}
+
+/// Levels of a pack completed one after another
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Replay {
+    pub pack: String,
+    pub levels: Vec<LevelRun>,
+}
+
+impl Replay {
+    pub fn new(pack: &str) -> Self {
+        Self {
+            pack: pack.to_owned(),
+            levels: Vec::new(),
+        }
+    }
+
+    pub fn total_ticks(&self) -> u64 {
+        self.levels.iter().map(|run| run.ticks).sum()
+    }
+
+    pub fn load(path: &std::path::Path) -> anyhow::Result<Self> {
+        Ok(serde_json::from_reader(std::io::BufReader::new(
+            std::fs::File::open(path)?,
+        ))?)
+    }
+
+    pub fn save(&self, path: &std::path::Path) -> anyhow::Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        serde_json::to_writer(std::io::BufWriter::new(std::fs::File::create(path)?), self)?;
+        Ok(())
+    }
+}
+
+/// Where the game keeps the replay of the latest run
+pub fn last_run_path() -> Option<std::path::PathBuf> {
+    pack::user_dir().and_then(|dir| Some(dir.parent()?.join("replays").join("last.json")))
+}
+
+/// Checksum of everything the simulation depends on besides the inputs
+pub fn fingerprint(config: &Config, level: &Level) -> u32 {
+    let physics = (
+        config.gravity,
+        config.bounciness,
+        config.friction,
+        config.tick_distance,
+        level.finish_radius(config),
+        &config.r#static,
+        &config.player,
+    );
+    let mut crc = flate2::Crc::new();
+    crc.update(&serde_json::to_vec(&(level, physics)).unwrap());
+    crc.sum()
+}
+
+#[derive(Debug)]
+pub enum VerifyError {
+    /// Recorded with a different level or physics config, so it can't be replayed
+    Mismatch,
+    Divergence {
+        tick: u64,
+        reason: String,
+    },
+}
+
+impl std::fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Mismatch => write!(f, "recorded with a different level or physics config"),
+            Self::Divergence { tick, reason } => write!(f, "diverged at tick {tick}: {reason}"),
+        }
+    }
+}
+
+/// Replays the inputs from the level start, succeeds if the finish is reached exactly on the last tick
+pub fn verify(config: &Config, level: &Level, run: &LevelRun) -> Result<(), VerifyError> {
+    if run.fingerprint != fingerprint(config, level) {
+        return Err(VerifyError::Mismatch);
+    }
+    if run.ticks == 0 {
+        return Err(VerifyError::Divergence {
+            tick: 0,
+            reason: "empty run".to_owned(),
+        });
+    }
+    let mut player = Player::new(config, level.start_pos);
+    let mut inputs = run.inputs.iter().peekable();
+    let mut input = match inputs.next() {
+        Some((0, input)) => *input,
+        _ => {
+            return Err(VerifyError::Divergence {
+                tick: 0,
+                reason: "no input for the first tick".to_owned(),
+            })
+        }
+    };
+    for tick in 0..run.ticks {
+        while let Some((_, next)) = inputs.next_if(|(at, _)| *at <= tick) {
+            input = *next;
+        }
+        let finished = player.step(config, level, &input).finished;
+        let ticks = tick + 1;
+        if ticks % CHECKPOINT_INTERVAL == 0 {
+            let index = (ticks / CHECKPOINT_INTERVAL - 1) as usize;
+            match run.checkpoints.get(index) {
+                Some(&pos) if (pos - player.pos).len() <= CHECKPOINT_TOLERANCE => {}
+                Some(&pos) => {
+                    return Err(VerifyError::Divergence {
+                        tick,
+                        reason: format!(
+                            "player at ({:.3}, {:.3}) instead of ({:.3}, {:.3})",
+                            player.pos.x, player.pos.y, pos.x, pos.y,
+                        ),
+                    })
+                }
+                None => {
+                    return Err(VerifyError::Divergence {
+                        tick,
+                        reason: "missing checkpoint".to_owned(),
+                    })
+                }
+            }
+        }
+        if finished && ticks < run.ticks {
+            return Err(VerifyError::Divergence {
+                tick,
+                reason: "finished earlier than recorded".to_owned(),
+            });
+        }
+        if !finished && ticks == run.ticks {
+            return Err(VerifyError::Divergence {
+                tick,
+                reason: "finish not reached".to_owned(),
+            });
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> Config {
+        toml::from_str(include_str!("../assets/config.toml")).unwrap()
+    }
+
+    /// Falling onto the finish lying on a floor
+    fn level() -> Level {
+        Level {
+            start_pos: vec2(0.0, 3.0),
+            finish_pos: vec2(0.0, -0.5),
+            surfaces: vec![Surface {
+                ends: [vec2(-5.0, -1.0), vec2(5.0, -1.0)],
+            }],
+            finish_radius: None,
+        }
+    }
+
+    fn record(config: &Config, level: &Level) -> LevelRun {
+        let mut run = LevelRun::new("test", config, level);
+        let mut player = Player::new(config, level.start_pos);
+        for tick in 0..physics::TICKS_PER_SECOND * 10 {
+            let scaling = if tick < physics::TICKS_PER_SECOND / 2 {
+                physics::Scaling::Grow
+            } else {
+                physics::Scaling::None
+            };
+            let input = physics::Input {
+                scaling,
+                cursor: player.pos + vec2(0.0, -player.radius),
+            };
+            let finished = player.step(config, level, &input).finished;
+            run.record(&input, &player);
+            if finished {
+                return run;
+            }
+        }
+        panic!("test run does not reach the finish");
+    }
+
+    #[test]
+    fn recorded_run_verifies() {
+        let config = config();
+        let run = record(&config, &level());
+        assert!(verify(&config, &level(), &run).is_ok());
+    }
+
+    #[test]
+    fn changed_level_or_config_rejected() {
+        let config = config();
+        let run = record(&config, &level());
+        let mut moved = level();
+        moved.surfaces[0].ends[0].y = -1.5;
+        assert!(matches!(
+            verify(&config, &moved, &run),
+            Err(VerifyError::Mismatch),
+        ));
+        let mut heavier = config.clone();
+        heavier.gravity *= 2.0;
+        assert!(matches!(
+            verify(&heavier, &level(), &run),
+            Err(VerifyError::Mismatch),
+        ));
+    }
+
+    #[test]
+    fn changed_inputs_diverge() {
+        let config = config();
+        let mut run = record(&config, &level());
+        run.inputs.truncate(1);
+        assert!(matches!(
+            verify(&config, &level(), &run),
+            Err(VerifyError::Divergence { .. }),
+        ));
+    }
+}
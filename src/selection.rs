@@ -0,0 +1,126 @@
+use super::*;
+
+use std::collections::BTreeSet;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TransformMode {
+    Move,
+    /// Hold Alt when starting the drag
+    Rotate,
+    /// Hold Shift when starting the drag
+    Scale,
+}
+
+pub enum Drag {
+    Box {
+        start: vec2<f32>,
+    },
+    Lasso {
+        points: Vec<vec2<f32>>,
+    },
+    Transform {
+        mode: TransformMode,
+        start: vec2<f32>,
+        pivot: vec2<f32>,
+        /// Selected surfaces as they were before the drag
+        original: Vec<(usize, Surface)>,
+        /// Selected start and finish positions before the drag
+        original_start: Option<vec2<f32>>,
+        original_finish: Option<vec2<f32>>,
+    },
+}
+
+impl Drag {
+    /// Area being dragged out by a box or lasso
+    pub fn area(&self, cursor: vec2<f32>) -> Option<Vec<vec2<f32>>> {
+        match self {
+            Self::Box { start } => Some(vec![
+                *start,
+                vec2(cursor.x, start.y),
+                cursor,
+                vec2(start.x, cursor.y),
+            ]),
+            Self::Lasso { points } => Some(points.clone()),
+            Self::Transform { .. } => None,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct Selection {
+    pub surfaces: BTreeSet<usize>,
+    /// Whether the start marker is selected, only box and lasso select markers
+    pub start: bool,
+    pub finish: bool,
+    /// Set with P, center of the selection bounds otherwise
+    pub custom_pivot: Option<vec2<f32>>,
+    pub drag: Option<Drag>,
+}
+
+impl Selection {
+    pub fn clear(&mut self) {
+        self.surfaces.clear();
+        self.start = false;
+        self.finish = false;
+        self.drag = None;
+    }
+
+    pub fn pivot(&self, level: &Level) -> Option<vec2<f32>> {
+        if let Some(pivot) = self.custom_pivot {
+            return Some(pivot);
+        }
+        let mut ends = self
+            .surfaces
+            .iter()
+            .flat_map(|&index| level.surfaces[index].ends);
+        let first = ends.next()?;
+        let (min, max) = ends.fold((first, first), |(min, max), p| {
+            (
+                vec2(min.x.min(p.x), min.y.min(p.y)),
+                vec2(max.x.max(p.x), max.y.max(p.y)),
+            )
+        });
+        Some((min + max) / 2.0)
+    }
+}
+
+/// Where a point ends up when dragging from `start` to `cursor`
+pub fn transform(
+    mode: TransformMode,
+    start: vec2<f32>,
+    pivot: vec2<f32>,
+    cursor: vec2<f32>,
+) -> impl Fn(vec2<f32>) -> vec2<f32> {
+    let from = start - pivot;
+    let to = cursor - pivot;
+    let angle = f32::atan2(to.y, to.x) - f32::atan2(from.y, from.x);
+    let scale = if from.len() > 1e-3 {
+        to.len() / from.len()
+    } else {
+        1.0
+    };
+    move |p| match mode {
+        TransformMode::Move => p + cursor - start,
+        TransformMode::Rotate => pivot + (p - pivot).rotate(Angle::from_radians(angle)),
+        TransformMode::Scale => pivot + (p - pivot) * scale,
+    }
+}
+
+/// Even-odd test, works for self intersecting lassos
+pub fn point_in_polygon(p: vec2<f32>, polygon: &[vec2<f32>]) -> bool {
+    // Cast a ray to the right, past the rightmost vertex
+    let far = polygon.iter().fold(p.x, |x, q| x.max(q.x)) + 1.0;
+    let edges = (0..polygon.len()).map(|i| [polygon[i], polygon[(i + 1) % polygon.len()]]);
+    validate::crosses_odd(edges, p, vec2(far, p.y))
+}
+
+/// Surfaces with both ends inside the area
+pub fn surfaces_in<'a>(level: &'a Level, area: &[vec2<f32>]) -> impl Iterator<Item = usize> + 'a {
+    let area = area.to_vec();
+    level
+        .surfaces
+        .iter()
+        .enumerate()
+        .filter(move |(_, surface)| surface.ends.iter().all(|&end| point_in_polygon(end, &area)))
+        .map(|(index, _)| index)
+}
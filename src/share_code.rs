@@ -0,0 +1,266 @@
+use super::*;
+
+use std::io::{Read, Write};
+
+const PREFIX: &str = "SC1.";
+
+/// Coordinates are stored in 1/1000th of a world unit
+const QUANTUM: f32 = 1e-3;
+
+fn quantize(p: vec2<f32>) -> vec2<i64> {
+    p.map(|x| (x / QUANTUM).round() as i64)
+}
+
+fn write_varint(bytes: &mut Vec<u8>, mut value: u64) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            bytes.push(byte);
+            return;
+        }
+        bytes.push(byte | 0x80);
+    }
+}
+
+fn read_varint(bytes: &mut impl Iterator<Item = u8>) -> anyhow::Result<u64> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let byte = bytes
+            .next()
+            .ok_or_else(|| anyhow::anyhow!("share code is truncated"))?;
+        value |= ((byte & 0x7f) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    anyhow::bail!("share code has a malformed number")
+}
+
+struct Encoder {
+    bytes: Vec<u8>,
+    last: vec2<i64>,
+}
+
+impl Encoder {
+    fn point(&mut self, p: vec2<f32>) {
+        let p = quantize(p);
+        for delta in [p.x - self.last.x, p.y - self.last.y] {
+            // zigzag so that small negative deltas stay small
+            write_varint(&mut self.bytes, ((delta << 1) ^ (delta >> 63)) as u64);
+        }
+        self.last = p;
+    }
+}
+
+struct Decoder<I> {
+    bytes: I,
+    last: vec2<i64>,
+}
+
+impl<I: Iterator<Item = u8>> Decoder<I> {
+    fn varint(&mut self) -> anyhow::Result<u64> {
+        read_varint(&mut self.bytes)
+    }
+    fn point(&mut self) -> anyhow::Result<vec2<f32>> {
+        let mut delta = || -> anyhow::Result<i64> {
+            let value = self.varint()?;
+            Ok((value >> 1) as i64 ^ -((value & 1) as i64))
+        };
+        let delta = vec2(delta()?, delta()?);
+        let (Some(x), Some(y)) = (
+            self.last.x.checked_add(delta.x),
+            self.last.y.checked_add(delta.y),
+        ) else {
+            anyhow::bail!("share code has a malformed point");
+        };
+        self.last = vec2(x, y);
+        Ok(self.last.map(|x| x as f32 * QUANTUM))
+    }
+}
+
+pub fn encode(level: &Level) -> String {
+    let mut encoder = Encoder {
+        bytes: Vec::new(),
+        last: vec2::ZERO,
+    };
+    encoder.point(level.start_pos);
+    encoder.point(level.finish_pos);
+    write_varint(&mut encoder.bytes, level.surfaces.len() as u64);
+    let mut prev_end = None;
+    for surface in &level.surfaces {
+        let [a, b] = surface.ends;
+        // Surfaces are mostly chained, so the start usually repeats previous end
+        let chained = prev_end.map_or(false, |end| quantize(end) == quantize(a));
+        encoder.bytes.push(chained as u8);
+        if !chained {
+            encoder.point(a);
+        }
+        encoder.point(b);
+        prev_end = Some(b);
+    }
+    // Optional so that codes of levels without it stay the same
+    if let Some(radius) = level.finish_radius {
+        write_varint(
+            &mut encoder.bytes,
+            (radius / QUANTUM).round().max(0.0) as u64,
+        );
+    }
+    to_code(&encoder.bytes)
+}
+
+/// Compresses and checksums the raw bytes
+fn to_code(bytes: &[u8]) -> String {
+    let mut crc = flate2::Crc::new();
+    crc.update(bytes);
+    let mut compressor =
+        flate2::write::DeflateEncoder::new(Vec::new(), flate2::Compression::best());
+    compressor.write_all(bytes).unwrap();
+    let mut data = compressor.finish().unwrap();
+    data.extend_from_slice(&crc.sum().to_le_bytes());
+
+    use base64::Engine as _;
+    format!(
+        "{PREFIX}{}",
+        base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(data),
+    )
+}
+
+pub fn decode(code: &str) -> anyhow::Result<Level> {
+    let code: String = code.chars().filter(|c| !c.is_whitespace()).collect();
+    let code = code
+        .strip_prefix(PREFIX)
+        .ok_or_else(|| anyhow::anyhow!("not a level share code"))?;
+    use base64::Engine as _;
+    let data = base64::engine::general_purpose::URL_SAFE_NO_PAD.decode(code)?;
+    if data.len() < 4 {
+        anyhow::bail!("share code is truncated");
+    }
+    let (compressed, checksum) = data.split_at(data.len() - 4);
+    let mut bytes = Vec::new();
+    flate2::read::DeflateDecoder::new(compressed).read_to_end(&mut bytes)?;
+    let mut crc = flate2::Crc::new();
+    crc.update(&bytes);
+    if crc.sum().to_le_bytes() != checksum {
+        anyhow::bail!("share code checksum mismatch");
+    }
+
+    let mut decoder = Decoder {
+        bytes: bytes.into_iter().peekable(),
+        last: vec2::ZERO,
+    };
+    let start_pos = decoder.point()?;
+    let finish_pos = decoder.point()?;
+    let count = decoder.varint()?;
+    let mut surfaces = Vec::new();
+    let mut prev_end = None;
+    for _ in 0..count {
+        let chained = decoder
+            .bytes
+            .next()
+            .ok_or_else(|| anyhow::anyhow!("share code is truncated"))?;
+        let a = match (chained, prev_end) {
+            (1, Some(end)) => end,
+            (0, _) => decoder.point()?,
+            _ => anyhow::bail!("share code has a malformed surface"),
+        };
+        let b = decoder.point()?;
+        surfaces.push(Surface { ends: [a, b] });
+        prev_end = Some(b);
+    }
+    let finish_radius = match decoder.bytes.peek() {
+        Some(_) => Some(decoder.varint()? as f32 * QUANTUM),
+        None => None,
+    };
+    if decoder.bytes.next().is_some() {
+        anyhow::bail!("share code has trailing data");
+    }
+    Ok(Level {
+        start_pos,
+        finish_pos,
+        surfaces,
+        finish_radius,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn level(finish_radius: Option<f32>) -> Level {
+        Level {
+            start_pos: vec2(-1.5, 0.25),
+            finish_pos: vec2(12.0, -3.125),
+            surfaces: vec![
+                Surface {
+                    ends: [vec2(-5.0, -1.0), vec2(5.0, -1.0)],
+                },
+                Surface {
+                    ends: [vec2(5.0, -1.0), vec2(5.0, 8.5)],
+                },
+                Surface {
+                    ends: [vec2(-2.0, 3.0), vec2(-4.0, 2.0)],
+                },
+            ],
+            finish_radius,
+        }
+    }
+
+    /// Everything stored in a code, at the precision it is stored with
+    fn quantized(level: &Level) -> (Vec<vec2<i64>>, Option<i64>) {
+        let points = [level.start_pos, level.finish_pos]
+            .into_iter()
+            .chain(level.surfaces.iter().flat_map(|surface| surface.ends))
+            .map(quantize)
+            .collect();
+        let radius = level
+            .finish_radius
+            .map(|radius| (radius / QUANTUM).round() as i64);
+        (points, radius)
+    }
+
+    fn assert_round_trip(level: &Level) {
+        let decoded = decode(&encode(level)).unwrap();
+        assert_eq!(quantized(level), quantized(&decoded));
+    }
+
+    #[test]
+    fn round_trip() {
+        assert_round_trip(&level(None));
+        assert_round_trip(&level(Some(0.75)));
+    }
+
+    #[test]
+    fn finish_radius_is_optional() {
+        let code = encode(&level(None));
+        assert_ne!(code, encode(&level(Some(0.75))));
+        assert_eq!(decode(&code).unwrap().finish_radius, None);
+    }
+
+    #[test]
+    fn whitespace_ignored() {
+        let code = encode(&level(None));
+        let (head, tail) = code.split_at(code.len() / 2);
+        assert!(decode(&format!(" {head}\n{tail} ")).is_ok());
+    }
+
+    #[test]
+    fn corrupted_rejected() {
+        let mut code = encode(&level(None));
+        code.pop();
+        assert!(decode(&code).is_err());
+        assert!(decode("not a code").is_err());
+    }
+
+    #[test]
+    fn overflow_rejected() {
+        let mut bytes = Vec::new();
+        // start at i64::MAX then move further by one
+        write_varint(&mut bytes, u64::MAX - 1);
+        write_varint(&mut bytes, 0);
+        write_varint(&mut bytes, 2);
+        write_varint(&mut bytes, 0);
+        write_varint(&mut bytes, 0);
+        assert!(decode(&to_code(&bytes)).is_err());
+    }
+}
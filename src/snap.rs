@@ -0,0 +1,115 @@
+use super::*;
+
+/// Which snapping is enabled, toggled from the editor panel
+#[derive(Debug, Clone)]
+pub struct Modes {
+    pub endpoints: bool,
+    pub midpoints: bool,
+    /// Closest point on a surface
+    pub surfaces: bool,
+    pub grid: bool,
+    /// Direction from the previous point in `EditorConfig::angle_step` increments
+    pub angle: bool,
+}
+
+impl Default for Modes {
+    fn default() -> Self {
+        Self {
+            endpoints: true,
+            midpoints: false,
+            surfaces: false,
+            grid: false,
+            angle: false,
+        }
+    }
+}
+
+fn round_to(x: f32, step: f32) -> f32 {
+    (x / step).round() * step
+}
+
+/// Endpoints and midpoints win over angle, then surfaces, then grid.
+/// `anchor` is the point a segment is being drawn from, surfaces for which `ignore` is true are not snapped to.
+pub fn snap(
+    modes: &Modes,
+    config: &EditorConfig,
+    level: &Level,
+    pos: vec2<f32>,
+    anchor: Option<vec2<f32>>,
+    ignore: impl Fn(usize) -> bool,
+) -> vec2<f32> {
+    let surfaces = || {
+        level
+            .surfaces
+            .iter()
+            .enumerate()
+            .filter(|&(index, _)| !ignore(index))
+            .map(|(_, surface)| surface)
+    };
+    let nearest = |points: &mut dyn Iterator<Item = vec2<f32>>| {
+        points
+            .filter(|&p| (p - pos).len() < config.snap_distance)
+            .min_by_key(|&p| r32((p - pos).len()))
+    };
+
+    if modes.endpoints {
+        if let Some(p) = nearest(&mut surfaces().flat_map(|surface| surface.ends)) {
+            return p;
+        }
+    }
+    if modes.midpoints {
+        if let Some(p) =
+            nearest(&mut surfaces().map(|surface| (surface.ends[0] + surface.ends[1]) / 2.0))
+        {
+            return p;
+        }
+    }
+    if let (true, Some(anchor)) = (modes.angle, anchor) {
+        let v = pos - anchor;
+        if v.len() > 1e-5 {
+            let step = config.angle_step.to_radians();
+            let angle = round_to(f32::atan2(v.y, v.x), step);
+            let direction = vec2(angle.cos(), angle.sin());
+            let mut length = vec2::dot(v, direction);
+            if modes.grid {
+                length = round_to(length, config.grid_spacing);
+            }
+            return anchor + direction * length;
+        }
+    }
+    if modes.surfaces {
+        if let Some(p) = nearest(&mut surfaces().map(|surface| surface.to(pos).closest_point)) {
+            return p;
+        }
+    }
+    if modes.grid {
+        return pos.map(|x| round_to(x, config.grid_spacing));
+    }
+    pos
+}
+
+pub fn modes_ui(ui: &mut egui::Ui, modes: &mut Modes, config: &mut EditorConfig) {
+    ui.horizontal(|ui| {
+        ui.checkbox(&mut modes.endpoints, "endpoints");
+        ui.checkbox(&mut modes.midpoints, "midpoints");
+        ui.checkbox(&mut modes.surfaces, "on surface");
+    });
+    ui.horizontal(|ui| {
+        ui.checkbox(&mut modes.grid, "grid");
+        ui.add(
+            egui::DragValue::new(&mut config.grid_spacing)
+                .speed(0.01)
+                .clamp_range(0.05..=10.0),
+        );
+    });
+    ui.horizontal(|ui| {
+        ui.checkbox(&mut modes.angle, "angle - Shift");
+        ui.add(
+            egui::DragValue::new(&mut config.angle_step)
+                .speed(1.0)
+                .clamp_range(1.0..=90.0)
+                .suffix("°"),
+        );
+    });
+    ui.label("no snapping - hold Alt");
+}
@@ -0,0 +1,607 @@
+use super::*;
+
+use std::str::FromStr;
+
+pub struct Options {
+    /// How many css pixels of the document make one world unit
+    pub pixels_per_unit: f32,
+    /// Max distance between a curve and its flattened polyline, in world units
+    pub tolerance: f32,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            pixels_per_unit: 100.0,
+            tolerance: 0.02,
+        }
+    }
+}
+
+/// 2d affine transform as in svg `matrix(a b c d e f)`
+#[derive(Copy, Clone)]
+struct Transform([f64; 6]);
+
+impl Transform {
+    const IDENTITY: Self = Self([1.0, 0.0, 0.0, 1.0, 0.0, 0.0]);
+
+    fn scale_translate(sx: f64, sy: f64, tx: f64, ty: f64) -> Self {
+        Self([sx, 0.0, 0.0, sy, tx, ty])
+    }
+
+    /// Apply `other` first, then `self`
+    fn then(self, other: Self) -> Self {
+        let [a1, b1, c1, d1, e1, f1] = self.0;
+        let [a2, b2, c2, d2, e2, f2] = other.0;
+        Self([
+            a1 * a2 + c1 * b2,
+            b1 * a2 + d1 * b2,
+            a1 * c2 + c1 * d2,
+            b1 * c2 + d1 * d2,
+            a1 * e2 + c1 * f2 + e1,
+            b1 * e2 + d1 * f2 + f1,
+        ])
+    }
+
+    /// Length that one unit along x is mapped to, for radii
+    fn scale(&self) -> f64 {
+        let [a, b, ..] = self.0;
+        a.hypot(b)
+    }
+
+    fn apply(&self, x: f64, y: f64) -> vec2<f32> {
+        let [a, b, c, d, e, f] = self.0;
+        vec2(a * x + c * y + e, b * x + d * y + f).map(|x| x as f32)
+    }
+}
+
+fn length_in_pixels(value: &str) -> Option<f64> {
+    let length = svgtypes::Length::from_str(value).ok()?;
+    let scale = match length.unit {
+        svgtypes::LengthUnit::None | svgtypes::LengthUnit::Px => 1.0,
+        svgtypes::LengthUnit::In => 96.0,
+        svgtypes::LengthUnit::Cm => 96.0 / 2.54,
+        svgtypes::LengthUnit::Mm => 96.0 / 25.4,
+        svgtypes::LengthUnit::Pt => 4.0 / 3.0,
+        svgtypes::LengthUnit::Pc => 16.0,
+        _ => return None,
+    };
+    Some(length.number * scale)
+}
+
+fn number(node: roxmltree::Node, name: &str) -> f64 {
+    node.attribute(name)
+        .and_then(|value| svgtypes::Number::from_str(value).ok())
+        .map_or(0.0, |number| number.0)
+}
+
+/// Maps root user units to world units, flipping y since svg y points down.
+/// The top left of the document is the world origin
+fn root_transform(root: roxmltree::Node, options: &Options) -> Transform {
+    let to_world = Transform::scale_translate(
+        1.0 / options.pixels_per_unit as f64,
+        -1.0 / options.pixels_per_unit as f64,
+        0.0,
+        0.0,
+    );
+    let Some(view_box) = root
+        .attribute("viewBox")
+        .and_then(|value| svgtypes::ViewBox::from_str(value).ok())
+        .filter(|view_box| view_box.w > 0.0 && view_box.h > 0.0)
+    else {
+        return to_world;
+    };
+    let width = root
+        .attribute("width")
+        .and_then(length_in_pixels)
+        .unwrap_or(view_box.w);
+    let height = root
+        .attribute("height")
+        .and_then(length_in_pixels)
+        .unwrap_or(view_box.h);
+    let aspect = root
+        .attribute("preserveAspectRatio")
+        .and_then(|value| svgtypes::AspectRatio::from_str(value).ok())
+        .unwrap_or_default();
+    let mut scale = (width / view_box.w, height / view_box.h);
+    if aspect.align != svgtypes::Align::None {
+        let uniform = if aspect.slice {
+            scale.0.max(scale.1)
+        } else {
+            scale.0.min(scale.1)
+        };
+        scale = (uniform, uniform);
+    }
+    // Space left over by a uniform scale, split according to the alignment
+    let (align_x, align_y) = match aspect.align {
+        svgtypes::Align::None | svgtypes::Align::XMinYMin => (0.0, 0.0),
+        svgtypes::Align::XMidYMin => (0.5, 0.0),
+        svgtypes::Align::XMaxYMin => (1.0, 0.0),
+        svgtypes::Align::XMinYMid => (0.0, 0.5),
+        svgtypes::Align::XMidYMid => (0.5, 0.5),
+        svgtypes::Align::XMaxYMid => (1.0, 0.5),
+        svgtypes::Align::XMinYMax => (0.0, 1.0),
+        svgtypes::Align::XMidYMax => (0.5, 1.0),
+        svgtypes::Align::XMaxYMax => (1.0, 1.0),
+    };
+    to_world.then(Transform::scale_translate(
+        scale.0,
+        scale.1,
+        (width - view_box.w * scale.0) * align_x - view_box.x * scale.0,
+        (height - view_box.h * scale.1) * align_y - view_box.y * scale.1,
+    ))
+}
+
+/// Curves are split in half at most this many times
+const MAX_FLATTEN_DEPTH: u32 = 16;
+
+fn flatten_cubic(points: [vec2<f32>; 4], tolerance: f32, result: &mut Vec<vec2<f32>>) {
+    flatten_cubic_with_depth(points, tolerance, MAX_FLATTEN_DEPTH, result);
+}
+
+fn flatten_cubic_with_depth(
+    points: [vec2<f32>; 4],
+    tolerance: f32,
+    depth: u32,
+    result: &mut Vec<vec2<f32>>,
+) {
+    let [p0, p1, p2, p3] = points;
+    let chord = Surface { ends: [p0, p3] };
+    if depth == 0 || chord.to(p1).distance.max(chord.to(p2).distance) <= tolerance {
+        result.push(p3);
+        return;
+    }
+    let mid = |a: vec2<f32>, b: vec2<f32>| (a + b) / 2.0;
+    let p01 = mid(p0, p1);
+    let p12 = mid(p1, p2);
+    let p23 = mid(p2, p3);
+    let p012 = mid(p01, p12);
+    let p123 = mid(p12, p23);
+    let center = mid(p012, p123);
+    flatten_cubic_with_depth([p0, p01, p012, center], tolerance, depth - 1, result);
+    flatten_cubic_with_depth([center, p123, p23, p3], tolerance, depth - 1, result);
+}
+
+#[derive(Default)]
+struct Polylines {
+    finished: Vec<Vec<vec2<f32>>>,
+    current: Vec<vec2<f32>>,
+}
+
+impl Polylines {
+    fn finish(&mut self) {
+        let current = std::mem::take(&mut self.current);
+        if current.len() >= 2 {
+            self.finished.push(current);
+        }
+    }
+    fn close(&mut self) {
+        if let Some(&first) = self.current.first() {
+            self.current.push(first);
+        }
+        self.finish();
+    }
+}
+
+fn path_polylines(d: &str, transform: Transform, tolerance: f32) -> Polylines {
+    let mut polylines = Polylines::default();
+    for segment in svgtypes::SimplifyingPathParser::from(d) {
+        let Ok(segment) = segment else {
+            break;
+        };
+        let last = polylines.current.last().copied();
+        match segment {
+            svgtypes::SimplePathSegment::MoveTo { x, y } => {
+                polylines.finish();
+                polylines.current.push(transform.apply(x, y));
+            }
+            svgtypes::SimplePathSegment::LineTo { x, y } => {
+                polylines.current.push(transform.apply(x, y));
+            }
+            svgtypes::SimplePathSegment::Quadratic { x1, y1, x, y } => {
+                let Some(p0) = last else { continue };
+                let p1 = transform.apply(x1, y1);
+                let p2 = transform.apply(x, y);
+                let c1 = p0 + (p1 - p0) * (2.0 / 3.0);
+                let c2 = p2 + (p1 - p2) * (2.0 / 3.0);
+                flatten_cubic([p0, c1, c2, p2], tolerance, &mut polylines.current);
+            }
+            svgtypes::SimplePathSegment::CurveTo {
+                x1,
+                y1,
+                x2,
+                y2,
+                x,
+                y,
+            } => {
+                let Some(p0) = last else { continue };
+                flatten_cubic(
+                    [
+                        p0,
+                        transform.apply(x1, y1),
+                        transform.apply(x2, y2),
+                        transform.apply(x, y),
+                    ],
+                    tolerance,
+                    &mut polylines.current,
+                );
+            }
+            svgtypes::SimplePathSegment::ClosePath => {
+                let first = polylines.current.first().copied();
+                polylines.close();
+                // Next segment without a moveto continues from the subpath start
+                polylines.current.extend(first);
+            }
+        }
+    }
+    polylines.finish();
+    polylines
+}
+
+fn element_transform(node: roxmltree::Node) -> Transform {
+    node.attribute("transform")
+        .and_then(|value| svgtypes::Transform::from_str(value).ok())
+        .map_or(Transform::IDENTITY, |t| {
+            Transform([t.a, t.b, t.c, t.d, t.e, t.f])
+        })
+}
+
+/// Circles or ellipses with id `start` and `finish` mark those positions
+/// and the radius of the finish one sets the finish radius,
+/// one with id `origin` marks the world origin in place of the top left of the document
+pub fn import(svg: &str, options: &Options) -> anyhow::Result<Level> {
+    if options.tolerance <= 0.0 {
+        anyhow::bail!("curve tolerance must be positive");
+    }
+    if options.pixels_per_unit <= 0.0 {
+        anyhow::bail!("pixels per unit must be positive");
+    }
+    let document = roxmltree::Document::parse(svg)?;
+    let root = document.root_element();
+    let mut level = Level {
+        start_pos: vec2::ZERO,
+        finish_pos: vec2(5.0, 0.0),
+        surfaces: Vec::new(),
+        finish_radius: None,
+    };
+    let mut markers = [None; 3];
+    let root_transform = root_transform(root, options);
+    let mut stack = vec![(root, root_transform)];
+    while let Some((node, parent_transform)) = stack.pop() {
+        let transform = parent_transform.then(element_transform(node));
+        // Reversed so that surfaces come out in document order
+        for child in node.children().filter(|child| child.is_element()).rev() {
+            if !matches!(
+                child.tag_name().name(),
+                "defs" | "clipPath" | "mask" | "symbol"
+            ) {
+                stack.push((child, transform));
+            }
+        }
+
+        let marker = match node.attribute("id") {
+            Some("start") => Some(0),
+            Some("finish") => Some(1),
+            Some("origin") => Some(2),
+            _ => None,
+        };
+        if let Some(marker) = marker {
+            if matches!(node.tag_name().name(), "circle" | "ellipse") {
+                markers[marker] = Some(transform.apply(number(node, "cx"), number(node, "cy")));
+                let radius = match node.tag_name().name() {
+                    "circle" => number(node, "r"),
+                    _ => number(node, "rx"),
+                };
+                if marker == 1 && radius > 0.0 {
+                    level.finish_radius = Some((radius * transform.scale()) as f32);
+                }
+                continue;
+            }
+        }
+
+        let mut polylines = Polylines::default();
+        match node.tag_name().name() {
+            "path" => {
+                polylines = path_polylines(
+                    node.attribute("d").unwrap_or(""),
+                    transform,
+                    options.tolerance,
+                );
+            }
+            "polyline" | "polygon" => {
+                polylines.current.extend(
+                    svgtypes::PointsParser::from(node.attribute("points").unwrap_or(""))
+                        .map(|(x, y)| transform.apply(x, y)),
+                );
+                if node.tag_name().name() == "polygon" {
+                    polylines.close();
+                }
+            }
+            "line" => {
+                polylines
+                    .current
+                    .push(transform.apply(number(node, "x1"), number(node, "y1")));
+                polylines
+                    .current
+                    .push(transform.apply(number(node, "x2"), number(node, "y2")));
+            }
+            "rect" => {
+                let (x, y) = (number(node, "x"), number(node, "y"));
+                let (w, h) = (number(node, "width"), number(node, "height"));
+                polylines.current.extend(
+                    [(x, y), (x + w, y), (x + w, y + h), (x, y + h)]
+                        .map(|(x, y)| transform.apply(x, y)),
+                );
+                polylines.close();
+            }
+            _ => {}
+        }
+        polylines.finish();
+        for polyline in polylines.finished {
+            level
+                .surfaces
+                .extend(
+                    polyline
+                        .windows(2)
+                        .filter(|ends| ends[0] != ends[1])
+                        .map(|ends| Surface {
+                            ends: [ends[0], ends[1]],
+                        }),
+                );
+        }
+    }
+    let [start, finish, origin] = markers;
+    let origin = origin.unwrap_or(vec2::ZERO);
+    for surface in &mut level.surfaces {
+        surface.ends = surface.ends.map(|end| end - origin);
+    }
+    if let Some(start) = start {
+        level.start_pos = start - origin;
+    }
+    if let Some(finish) = finish {
+        level.finish_pos = finish - origin;
+    }
+    Ok(level)
+}
+
+/// The finish circle has the level's finish radius, or the config one if it has none
+pub fn export(level: &Level, config: &Config, options: &Options) -> String {
+    let scale = options.pixels_per_unit;
+    let to_svg = |p: vec2<f32>| vec2(p.x * scale, -p.y * scale);
+    let points = level
+        .surfaces
+        .iter()
+        .flat_map(|surface| surface.ends)
+        .chain([level.start_pos, level.finish_pos])
+        .map(to_svg);
+    let (min, max) = points.fold(
+        (vec2::splat(f32::INFINITY), vec2::splat(-f32::INFINITY)),
+        |(min, max), p| {
+            (
+                vec2(min.x.min(p.x), min.y.min(p.y)),
+                vec2(max.x.max(p.x), max.y.max(p.y)),
+            )
+        },
+    );
+    let margin = scale;
+    let min = min - vec2::splat(margin);
+    let size = max - min + vec2::splat(margin);
+
+    let mut d = String::new();
+    let mut prev_end = None;
+    for surface in &level.surfaces {
+        let [a, b] = surface.ends.map(to_svg);
+        if prev_end != Some(surface.ends[0]) {
+            d += &format!("M {} {} ", a.x, a.y);
+        }
+        d += &format!("L {} {} ", b.x, b.y);
+        prev_end = Some(surface.ends[1]);
+    }
+
+    let origin = to_svg(vec2::ZERO);
+    let start = to_svg(level.start_pos);
+    let finish = to_svg(level.finish_pos);
+    let mut svg = String::new();
+    svg += &format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"{} {} {} {}\" width=\"{}\" height=\"{}\">\n",
+        min.x, min.y, size.x, size.y, size.x, size.y,
+    );
+    svg += &format!(
+        "  <path id=\"surfaces\" d=\"{}\" fill=\"none\" stroke=\"black\" stroke-width=\"2\"/>\n",
+        d.trim_end(),
+    );
+    // Keeps the coordinates when imported back, the view box only frames the level
+    svg += &format!(
+        "  <circle id=\"origin\" cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"none\" stroke=\"gray\"/>\n",
+        origin.x,
+        origin.y,
+        scale * 0.05,
+    );
+    svg += &format!(
+        "  <circle id=\"start\" cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"blue\"/>\n",
+        start.x,
+        start.y,
+        scale * 0.1,
+    );
+    svg += &format!(
+        "  <circle id=\"finish\" cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"green\"/>\n",
+        finish.x,
+        finish.y,
+        scale * level.finish_radius(config),
+    );
+    svg += "</svg>\n";
+    svg
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// One world unit per user unit
+    fn options() -> Options {
+        Options {
+            pixels_per_unit: 1.0,
+            ..default()
+        }
+    }
+
+    fn import_ends(svg: &str) -> Vec<[(f32, f32); 2]> {
+        import(svg, &options())
+            .unwrap()
+            .surfaces
+            .iter()
+            .map(|surface| surface.ends.map(|end| (end.x, end.y)))
+            .collect()
+    }
+
+    fn assert_close(a: vec2<f32>, b: vec2<f32>) {
+        assert!((a - b).len() < 1e-4, "{a:?} != {b:?}");
+    }
+
+    #[test]
+    fn path_commands() {
+        let ends = import_ends(r#"<svg><path d="M 0 0 L 10 0 H 20 V -10 Z"/></svg>"#);
+        assert_eq!(
+            ends,
+            [
+                [(0.0, 0.0), (10.0, 0.0)],
+                [(10.0, 0.0), (20.0, 0.0)],
+                [(20.0, 0.0), (20.0, 10.0)],
+                [(20.0, 10.0), (0.0, 0.0)],
+            ],
+        );
+    }
+
+    #[test]
+    fn relative_coordinates() {
+        let absolute = import_ends(r#"<svg><path d="M 5 5 L 10 5 L 10 10 M 0 0 L 1 1"/></svg>"#);
+        let relative = import_ends(r#"<svg><path d="m 5 5 l 5 0 v 5 m -10 -10 l 1 1"/></svg>"#);
+        assert_eq!(absolute, relative);
+    }
+
+    #[test]
+    fn curves_are_flattened() {
+        let level = import(
+            r#"<svg><path d="M 0 0 C 0 10 10 10 10 0 Q 15 -10 20 0"/></svg>"#,
+            &Options {
+                tolerance: 0.01,
+                ..options()
+            },
+        )
+        .unwrap();
+        assert!(level.surfaces.len() > 4);
+        assert_close(level.surfaces[0].ends[0], vec2::ZERO);
+        assert_close(level.surfaces.last().unwrap().ends[1], vec2(20.0, 0.0));
+        for pair in level.surfaces.windows(2) {
+            assert_eq!(pair[0].ends[1], pair[1].ends[0]);
+        }
+    }
+
+    #[test]
+    fn flatten_depth_is_limited() {
+        let mut result = Vec::new();
+        let points = [
+            vec2(0.0, 0.0),
+            vec2(0.0, 1e6),
+            vec2(1e6, 1e6),
+            vec2(1e6, 0.0),
+        ];
+        flatten_cubic(points, 1e-9, &mut result);
+        assert!(result.len() <= 1 << MAX_FLATTEN_DEPTH);
+        assert_eq!(result.last(), Some(&points[3]));
+    }
+
+    #[test]
+    fn invalid_options_rejected() {
+        let svg = r#"<svg><path d="M 0 0 C 0 10 10 10 10 0"/></svg>"#;
+        for options in [
+            Options {
+                tolerance: 0.0,
+                ..options()
+            },
+            Options {
+                pixels_per_unit: -1.0,
+                ..options()
+            },
+        ] {
+            assert!(import(svg, &options).is_err());
+        }
+    }
+
+    #[test]
+    fn transforms() {
+        let ends = import_ends(
+            r#"<svg><g transform="translate(10 20)"><line x1="0" y1="0" x2="1" y2="0" transform="scale(2)"/></g></svg>"#,
+        );
+        assert_eq!(ends, [[(10.0, -20.0), (12.0, -20.0)]]);
+    }
+
+    #[test]
+    fn view_box() {
+        // Document is 200 pixels wide, showing user space from 100 to 200
+        let ends = import_ends(
+            r#"<svg width="200" height="200" viewBox="100 100 100 100"><line x1="100" y1="100" x2="150" y2="100"/></svg>"#,
+        );
+        assert_eq!(ends, [[(0.0, 0.0), (100.0, 0.0)]]);
+    }
+
+    #[test]
+    fn preserve_aspect_ratio() {
+        let svg = |aspect: &str| {
+            format!(
+                r#"<svg width="200" height="100" viewBox="0 0 100 100" preserveAspectRatio="{aspect}"><line x1="0" y1="0" x2="100" y2="0"/></svg>"#,
+            )
+        };
+        // Uniform scale of one, centered horizontally by default
+        assert_eq!(
+            import_ends(&svg("xMidYMid meet")),
+            [[(50.0, 0.0), (150.0, 0.0)]],
+        );
+        assert_eq!(
+            import_ends(&svg("xMinYMin meet")),
+            [[(0.0, 0.0), (100.0, 0.0)]],
+        );
+        assert_eq!(import_ends(&svg("none")), [[(0.0, 0.0), (200.0, 0.0)]]);
+        assert_eq!(
+            import_ends(&svg("xMidYMid slice")),
+            [[(0.0, 50.0), (200.0, 50.0)]],
+        );
+    }
+
+    #[test]
+    fn export_round_trip() {
+        let level = Level {
+            start_pos: vec2(-1.5, 2.0),
+            finish_pos: vec2(7.0, -3.0),
+            surfaces: vec![
+                Surface {
+                    ends: [vec2(-5.0, -1.0), vec2(5.0, -1.0)],
+                },
+                Surface {
+                    ends: [vec2(5.0, -1.0), vec2(5.0, 8.5)],
+                },
+                Surface {
+                    ends: [vec2(-2.0, 3.0), vec2(-4.0, 2.0)],
+                },
+            ],
+            finish_radius: Some(0.35),
+        };
+        let config: Config = toml::from_str(include_str!("../assets/config.toml")).unwrap();
+        let options = Options::default();
+        let imported = import(&export(&level, &config, &options), &options).unwrap();
+        assert_close(imported.start_pos, level.start_pos);
+        assert_close(imported.finish_pos, level.finish_pos);
+        assert!((imported.finish_radius.unwrap() - 0.35).abs() < 1e-4);
+        let level = Level {
+            finish_radius: None,
+            ..level
+        };
+        let imported = import(&export(&level, &config, &options), &options).unwrap();
+        assert_eq!(imported.finish_radius, Some(config.finish_radius));
+        assert_eq!(imported.surfaces.len(), level.surfaces.len());
+        for (a, b) in imported.surfaces.iter().zip(&level.surfaces) {
+            assert_close(a.ends[0], b.ends[0]);
+            assert_close(a.ends[1], b.ends[1]);
+        }
+    }
+}
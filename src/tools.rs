@@ -0,0 +1,314 @@
+use super::*;
+
+use std::path::Path;
+
+/// Level file formats, detected by extension
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Format {
+    Json,
+    Svg,
+    /// Text file with a share code
+    ShareCode,
+}
+
+impl Format {
+    pub fn detect(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("svg") => Self::Svg,
+            Some("txt") | Some("code") => Self::ShareCode,
+            _ => Self::Json,
+        }
+    }
+}
+
+pub fn load(path: &Path, svg: &svg::Options) -> anyhow::Result<Level> {
+    match Format::detect(path) {
+        Format::Json => migrations::load_file(path),
+        Format::Svg => svg::import(&std::fs::read_to_string(path)?, svg),
+        Format::ShareCode => share_code::decode(std::fs::read_to_string(path)?.trim()),
+    }
+}
+
+/// The config gives svg files the finish radius of levels that have none
+pub fn save(path: &Path, level: &Level, config: &Config, svg: &svg::Options) -> anyhow::Result<()> {
+    match Format::detect(path) {
+        Format::Json => migrations::save_file(path, level),
+        Format::Svg => Ok(std::fs::write(path, svg::export(level, config, svg))?),
+        Format::ShareCode => Ok(std::fs::write(path, share_code::encode(level) + "\n")?),
+    }
+}
+
+pub struct Stats {
+    pub surfaces: usize,
+    pub min: vec2<f32>,
+    pub max: vec2<f32>,
+    pub total_length: f32,
+}
+
+impl Stats {
+    pub fn new(level: &Level) -> Self {
+        let points = level
+            .surfaces
+            .iter()
+            .flat_map(|surface| surface.ends)
+            .chain([level.start_pos, level.finish_pos]);
+        let (min, max) = points.fold(
+            (vec2::splat(f32::INFINITY), vec2::splat(f32::NEG_INFINITY)),
+            |(min, max), p| {
+                (
+                    vec2(min.x.min(p.x), min.y.min(p.y)),
+                    vec2(max.x.max(p.x), max.y.max(p.y)),
+                )
+            },
+        );
+        Self {
+            surfaces: level.surfaces.len(),
+            min,
+            max,
+            total_length: level
+                .surfaces
+                .iter()
+                .map(|surface| (surface.ends[1] - surface.ends[0]).len())
+                .sum(),
+        }
+    }
+}
+
+impl std::fmt::Display for Stats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let size = self.max - self.min;
+        writeln!(f, "surfaces: {}", self.surfaces)?;
+        writeln!(
+            f,
+            "bounds: ({:.3}, {:.3}) - ({:.3}, {:.3}), size {:.3} x {:.3}",
+            self.min.x, self.min.y, self.max.x, self.max.y, size.x, size.y,
+        )?;
+        write!(f, "total length: {:.3}", self.total_length)
+    }
+}
+
+#[derive(Default)]
+pub struct NormalizeReport {
+    pub merged_endpoints: usize,
+    pub removed_degenerate: usize,
+    pub removed_duplicates: usize,
+}
+
+fn same_surface(a: &Surface, b: &Surface) -> bool {
+    a.ends == b.ends || a.ends == [b.ends[1], b.ends[0]]
+}
+
+/// Snaps endpoints closer than `merge_distance` together, rounds coordinates to `precision`
+/// and removes the surfaces that became zero length or duplicated
+pub fn normalize(level: &mut Level, precision: f32, merge_distance: f32) -> NormalizeReport {
+    let mut report = NormalizeReport::default();
+    let round = |p: vec2<f32>| p.map(|x| (x / precision).round() * precision);
+
+    let mut anchors: Vec<vec2<f32>> = Vec::new();
+    for surface in &mut level.surfaces {
+        for end in &mut surface.ends {
+            match anchors
+                .iter()
+                .find(|anchor| (**anchor - *end).len() < merge_distance)
+            {
+                Some(&anchor) => {
+                    if anchor != *end {
+                        report.merged_endpoints += 1;
+                    }
+                    *end = anchor;
+                }
+                None => {
+                    *end = round(*end);
+                    anchors.push(*end);
+                }
+            }
+        }
+    }
+    level.start_pos = round(level.start_pos);
+    level.finish_pos = round(level.finish_pos);
+
+    let mut surfaces: Vec<Surface> = Vec::new();
+    for surface in level.surfaces.drain(..) {
+        if surface.ends[0] == surface.ends[1] {
+            report.removed_degenerate += 1;
+        } else if surfaces.iter().any(|other| same_surface(other, &surface)) {
+            report.removed_duplicates += 1;
+        } else {
+            surfaces.push(surface);
+        }
+    }
+    level.surfaces = surfaces;
+    report
+}
+
+impl std::fmt::Display for NormalizeReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "merged {} endpoints, removed {} zero length and {} duplicate surfaces",
+            self.merged_endpoints, self.removed_degenerate, self.removed_duplicates,
+        )
+    }
+}
+
+fn point(p: vec2<f32>) -> String {
+    format!("({:.3}, {:.3})", p.x, p.y)
+}
+
+/// Human readable differences, surfaces are compared regardless of order and direction
+pub fn diff(a: &Level, b: &Level, tolerance: f32) -> Vec<String> {
+    let close = |p: vec2<f32>, q: vec2<f32>| (p - q).len() <= tolerance;
+    let matches = |s: &Surface, t: &Surface| {
+        (close(s.ends[0], t.ends[0]) && close(s.ends[1], t.ends[1]))
+            || (close(s.ends[0], t.ends[1]) && close(s.ends[1], t.ends[0]))
+    };
+    let mut changes = Vec::new();
+    if !close(a.start_pos, b.start_pos) {
+        changes.push(format!(
+            "start moved {} -> {}",
+            point(a.start_pos),
+            point(b.start_pos)
+        ));
+    }
+    if !close(a.finish_pos, b.finish_pos) {
+        changes.push(format!(
+            "finish moved {} -> {}",
+            point(a.finish_pos),
+            point(b.finish_pos)
+        ));
+    }
+    if a.finish_radius != b.finish_radius {
+        changes.push(format!(
+            "finish radius {:?} -> {:?}",
+            a.finish_radius, b.finish_radius
+        ));
+    }
+    // Each surface matches at most one other, so duplicates count
+    let mut added: Vec<&Surface> = b.surfaces.iter().collect();
+    for surface in &a.surfaces {
+        match added.iter().position(|other| matches(surface, other)) {
+            Some(index) => {
+                added.remove(index);
+            }
+            None => changes.push(format!(
+                "- {} {}",
+                point(surface.ends[0]),
+                point(surface.ends[1])
+            )),
+        }
+    }
+    for surface in added {
+        changes.push(format!(
+            "+ {} {}",
+            point(surface.ends[0]),
+            point(surface.ends[1])
+        ));
+    }
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn surface(a: (f32, f32), b: (f32, f32)) -> Surface {
+        Surface {
+            ends: [vec2(a.0, a.1), vec2(b.0, b.1)],
+        }
+    }
+
+    fn level(surfaces: Vec<Surface>) -> Level {
+        Level {
+            start_pos: vec2(0.5, 0.5),
+            finish_pos: vec2(3.0, 1.0),
+            surfaces,
+            finish_radius: None,
+        }
+    }
+
+    #[test]
+    fn formats_round_trip() {
+        // Svg files always carry a finish radius
+        let level = Level {
+            finish_radius: Some(0.5),
+            ..level(vec![
+                surface((0.0, 0.0), (4.0, 0.0)),
+                surface((4.0, 0.0), (4.0, 2.0)),
+            ])
+        };
+        let config: Config = toml::from_str(include_str!("../assets/config.toml")).unwrap();
+        let dir = std::env::temp_dir().join(format!("{}-tools-test", env!("CARGO_PKG_NAME")));
+        std::fs::create_dir_all(&dir).unwrap();
+        for file in ["level.json", "level.svg", "level.txt"] {
+            let path = dir.join(file);
+            save(&path, &level, &config, &default()).unwrap();
+            let loaded = load(&path, &default()).unwrap();
+            assert_eq!(diff(&level, &loaded, 0.001), Vec::<String>::new(), "{file}");
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn stats() {
+        let stats = Stats::new(&level(vec![
+            surface((0.0, 0.0), (4.0, 0.0)),
+            surface((4.0, 0.0), (4.0, 2.0)),
+        ]));
+        assert_eq!(stats.surfaces, 2);
+        assert_eq!(stats.min, vec2(0.0, 0.0));
+        assert_eq!(stats.max, vec2(4.0, 2.0));
+        assert_eq!(stats.total_length, 6.0);
+    }
+
+    #[test]
+    fn normalize_merges_and_removes() {
+        let mut level = level(vec![
+            surface((0.0, 0.0), (1.0001, 0.0)),
+            surface((1.0, 0.0004), (1.0, 1.0)),
+            surface((1.0, 1.0), (1.0, 1.0)),
+            surface((1.0, 1.0), (1.0, 0.0)),
+        ]);
+        let report = normalize(&mut level, 0.001, 0.01);
+        assert_eq!(report.merged_endpoints, 1);
+        assert_eq!(report.removed_degenerate, 1);
+        assert_eq!(report.removed_duplicates, 1);
+        assert_eq!(level.surfaces.len(), 2);
+        assert_eq!(level.surfaces[0].ends[1], level.surfaces[1].ends[0]);
+    }
+
+    #[test]
+    fn diff_ignores_order_and_direction() {
+        let a = level(vec![
+            surface((0.0, 0.0), (1.0, 0.0)),
+            surface((1.0, 0.0), (1.0, 1.0)),
+        ]);
+        let b = level(vec![
+            surface((1.0, 1.0), (1.0, 0.0)),
+            surface((0.0, 0.0), (1.0, 0.0005)),
+        ]);
+        assert!(diff(&a, &b, 0.001).is_empty());
+        assert_eq!(diff(&a, &b, 0.0001).len(), 2);
+    }
+
+    #[test]
+    fn diff_counts_duplicates() {
+        let a = level(vec![surface((0.0, 0.0), (1.0, 0.0))]);
+        let b = level(vec![
+            surface((0.0, 0.0), (1.0, 0.0)),
+            surface((1.0, 0.0), (0.0, 0.0)),
+        ]);
+        assert_eq!(diff(&a, &b, 0.001), ["+ (1.000, 0.000) (0.000, 0.000)"],);
+        assert_eq!(diff(&b, &a, 0.001), ["- (1.000, 0.000) (0.000, 0.000)"],);
+    }
+
+    #[test]
+    fn diff_start_finish() {
+        let a = level(Vec::new());
+        let b = Level {
+            start_pos: vec2(1.0, 0.5),
+            finish_radius: Some(0.5),
+            ..level(Vec::new())
+        };
+        assert_eq!(diff(&a, &b, 0.001).len(), 2);
+    }
+}
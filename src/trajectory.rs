@@ -0,0 +1,139 @@
+use super::*;
+
+/// Ticks between the player outlines drawn along the path
+pub const OUTLINE_INTERVAL: usize = physics::TICKS_PER_SECOND as usize / 4;
+
+/// Input held for a number of ticks
+#[derive(Debug, Clone)]
+pub struct Segment {
+    pub scaling: physics::Scaling,
+    pub ticks: u64,
+    /// In degrees, direction from the player center to where the cursor is kept
+    pub direction: f32,
+}
+
+/// Control sequence simulated from a point picked in the editor
+pub struct Preview {
+    pub start: Option<vec2<f32>>,
+    pub segments: Vec<Segment>,
+}
+
+impl Default for Preview {
+    fn default() -> Self {
+        Self {
+            start: None,
+            segments: vec![
+                Segment {
+                    scaling: physics::Scaling::Grow,
+                    ticks: physics::TICKS_PER_SECOND / 5,
+                    direction: -90.0,
+                },
+                Segment {
+                    scaling: physics::Scaling::None,
+                    ticks: physics::TICKS_PER_SECOND * 3,
+                    direction: -90.0,
+                },
+            ],
+        }
+    }
+}
+
+pub struct Path {
+    /// Player position and radius after every tick, starting with the spawn
+    pub states: Vec<(vec2<f32>, f32)>,
+    pub finish_tick: Option<u64>,
+}
+
+impl Preview {
+    /// Steps the player the same way as the game, stops at the finish
+    pub fn simulate(&self, config: &Config, level: &Level) -> Option<Path> {
+        let mut player = Player::new(config, self.start?);
+        let mut path = Path {
+            states: vec![(player.pos, player.radius)],
+            finish_tick: None,
+        };
+        for segment in &self.segments {
+            let direction = vec2(1.0, 0.0).rotate(Angle::from_degrees(segment.direction));
+            for _ in 0..segment.ticks {
+                let input = physics::Input {
+                    scaling: segment.scaling,
+                    cursor: player.pos + direction * player.radius,
+                };
+                let events = player.step(config, level, &input);
+                path.states.push((player.pos, player.radius));
+                if events.finished {
+                    path.finish_tick = Some(path.states.len() as u64 - 1);
+                    return Some(path);
+                }
+            }
+        }
+        Some(path)
+    }
+}
+
+/// Returns whether the segments changed
+pub fn controls_ui(ui: &mut egui::Ui, preview: &mut Preview, path: Option<&Path>) -> bool {
+    let mut changed = false;
+    let mut remove = None;
+    for (index, segment) in preview.segments.iter_mut().enumerate() {
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_id_source(("trajectory segment", index))
+                .selected_text(format!("{:?}", segment.scaling))
+                .width(70.0)
+                .show_ui(ui, |ui| {
+                    for scaling in [
+                        physics::Scaling::None,
+                        physics::Scaling::Grow,
+                        physics::Scaling::Shrink,
+                    ] {
+                        changed |= ui
+                            .selectable_value(&mut segment.scaling, scaling, format!("{scaling:?}"))
+                            .changed();
+                    }
+                });
+            changed |= ui
+                .add(
+                    egui::DragValue::new(&mut segment.ticks)
+                        .clamp_range(0..=physics::TICKS_PER_SECOND * 60)
+                        .suffix(" ticks"),
+                )
+                .changed();
+            changed |= ui
+                .add(
+                    egui::DragValue::new(&mut segment.direction)
+                        .speed(1.0)
+                        .clamp_range(-180.0..=180.0)
+                        .suffix("°"),
+                )
+                .changed();
+            if ui.small_button("x").clicked() {
+                remove = Some(index);
+            }
+        });
+    }
+    if let Some(index) = remove {
+        preview.segments.remove(index);
+        changed = true;
+    }
+    if ui.button("add").clicked() {
+        let last = preview.segments.last().cloned();
+        preview.segments.push(last.unwrap_or(Segment {
+            scaling: physics::Scaling::None,
+            ticks: physics::TICKS_PER_SECOND,
+            direction: -90.0,
+        }));
+        changed = true;
+    }
+    match path {
+        None => ui.label("pick a start point - LMB"),
+        Some(Path {
+            finish_tick: Some(tick),
+            ..
+        }) => ui.label(format!(
+            "reaches the finish at {}",
+            format_time(physics::ticks_to_seconds(*tick))
+        )),
+        Some(_) => ui.label("does not reach the finish"),
+    };
+    changed
+}
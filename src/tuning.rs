@@ -0,0 +1,161 @@
+use super::*;
+
+fn slider(
+    ui: &mut egui::Ui,
+    value: &mut f32,
+    range: std::ops::RangeInclusive<f32>,
+    name: &str,
+) -> bool {
+    ui.add(egui::Slider::new(value, range).text(name)).changed()
+}
+
+/// Sliders for every numeric field, returns whether anything changed.
+/// Cursor hotspot is left out since it only applies together with the cursor image.
+pub fn config_ui(ui: &mut egui::Ui, config: &mut Config) -> bool {
+    let mut changed = false;
+    ui.collapsing("physics", |ui| {
+        changed |= slider(ui, &mut config.gravity, 0.0..=20.0, "gravity");
+        changed |= slider(ui, &mut config.bounciness, 0.0..=1.0, "bounciness");
+        changed |= slider(ui, &mut config.friction, 0.0..=20.0, "friction");
+        changed |= slider(ui, &mut config.tick_distance, 0.001..=0.1, "tick distance");
+        changed |= slider(ui, &mut config.finish_radius, 0.05..=1.0, "finish radius");
+    });
+    ui.collapsing("static", |ui| {
+        let config = &mut config.r#static;
+        changed |= slider(ui, &mut config.max_vel, 0.0..=1.0, "max vel");
+        changed |= slider(ui, &mut config.time_to_full, 0.01..=5.0, "time to full");
+    });
+    ui.collapsing("player", |ui| {
+        let config = &mut config.player;
+        // Each end is limited by the other one so that they stay ordered,
+        // and the start radius stays between them
+        let max = config.max_radius.clamp(0.01, 1.0);
+        changed |= slider(ui, &mut config.min_radius, 0.01..=max, "min radius");
+        let min = config.min_radius.clamp(0.01, 2.0);
+        changed |= slider(ui, &mut config.max_radius, min..=2.0, "max radius");
+        let clamped = config.radius.max(config.min_radius).min(config.max_radius);
+        if clamped != config.radius {
+            config.radius = clamped;
+            changed = true;
+        }
+        let range = config.min_radius..=config.max_radius;
+        changed |= slider(ui, &mut config.radius, range, "radius");
+        changed |= slider(ui, &mut config.scaling_speed, 0.0..=20.0, "scaling speed");
+    });
+    ui.collapsing("camera", |ui| {
+        let config = &mut config.camera;
+        changed |= slider(ui, &mut config.fov, 1.0..=50.0, "fov");
+        changed |= slider(ui, &mut config.speed, 0.0..=10.0, "speed");
+    });
+    ui.collapsing("sfx", |ui| {
+        let config = &mut config.sfx;
+        changed |= slider(ui, &mut config.master_volume, 0.0..=5.0, "master volume");
+        changed |= slider(ui, &mut config.music_volume, 0.0..=1.0, "music volume");
+        changed |= slider(ui, &mut config.level_volume, 0.0..=1.0, "level volume");
+        changed |= slider(ui, &mut config.win_volume, 0.0..=1.0, "win volume");
+        changed |= slider(ui, &mut config.hit_volume, 0.0..=1.0, "hit volume");
+        changed |= slider(
+            ui,
+            &mut config.hit_max_volume_speed,
+            0.1..=10.0,
+            "hit max volume speed",
+        );
+        changed |= slider(
+            ui,
+            &mut config.scaling_max_volume,
+            0.1..=10.0,
+            "scaling max volume",
+        );
+    });
+    ui.collapsing("editor", |ui| {
+        let config = &mut config.editor;
+        changed |= slider(ui, &mut config.snap_distance, 0.01..=1.0, "snap distance");
+        changed |= slider(
+            ui,
+            &mut config.cursor_rotation_speed,
+            0.0..=720.0,
+            "cursor rotation speed",
+        );
+        changed |= slider(ui, &mut config.camera_speed, 0.0..=20.0, "camera speed");
+        changed |= slider(ui, &mut config.grid_spacing, 0.05..=10.0, "grid spacing");
+        changed |= slider(ui, &mut config.angle_step, 1.0..=90.0, "angle step");
+        changed |= slider(
+            ui,
+            &mut config.autosave_interval,
+            0.0..=600.0,
+            "autosave interval",
+        );
+        ui.horizontal(|ui| {
+            changed |= ui
+                .add(egui::DragValue::new(&mut config.backups).clamp_range(0..=100))
+                .changed();
+            ui.label("backups per level");
+        });
+    });
+    ui.collapsing("level mesh", |ui| {
+        changed |= slider(
+            ui,
+            &mut config.level_mesh.max_distance,
+            0.005..=0.5,
+            "max distance",
+        );
+    });
+    changed
+}
+
+/// Rejects values the physics can't work with
+pub fn validate(config: &Config) -> anyhow::Result<()> {
+    let player = &config.player;
+    // Also false for NaN
+    let ordered = player.min_radius > 0.0 && player.min_radius <= player.max_radius;
+    if !ordered {
+        anyhow::bail!(
+            "player radius range {}..{} is invalid",
+            player.min_radius,
+            player.max_radius,
+        );
+    }
+    if !(player.min_radius..=player.max_radius).contains(&player.radius) {
+        anyhow::bail!(
+            "player radius {} is outside of the range {}..{}",
+            player.radius,
+            player.min_radius,
+            player.max_radius,
+        );
+    }
+    Ok(())
+}
+
+pub fn save(config: &Config) -> anyhow::Result<()> {
+    validate(config)?;
+    files::write_atomic(&config_path(), toml::to_string(config)?.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> Config {
+        toml::from_str(include_str!("../assets/config.toml")).unwrap()
+    }
+
+    #[test]
+    fn shipped_config_is_valid() {
+        assert!(validate(&config()).is_ok());
+    }
+
+    #[test]
+    fn radius_range_checked() {
+        let mut inverted = config();
+        inverted.player.min_radius = inverted.player.max_radius + 0.1;
+        assert!(validate(&inverted).is_err());
+
+        let mut config = config();
+        config.player.radius = config.player.max_radius + 0.1;
+        assert!(validate(&config).is_err());
+        config.player.radius = config.player.min_radius - 0.01;
+        assert!(validate(&config).is_err());
+        config.player.radius = config.player.min_radius;
+        assert!(validate(&config).is_ok());
+    }
+}
@@ -0,0 +1,343 @@
+use super::*;
+
+const EPS: f32 = 1e-5;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum IssueKind {
+    ZeroLength,
+    Duplicate,
+    NearMiss,
+    SelfIntersection,
+    OpenLoop,
+    StartInsideGeometry,
+    FinishOverlapsWall,
+}
+
+impl IssueKind {
+    pub fn description(&self) -> &'static str {
+        match self {
+            Self::ZeroLength => "zero-length surface",
+            Self::Duplicate => "duplicate surface",
+            Self::NearMiss => "endpoints should be snapped together",
+            Self::SelfIntersection => "surfaces intersect",
+            Self::OpenLoop => "open loop breaks insides fill",
+            Self::StartInsideGeometry => "start is inside geometry",
+            Self::FinishOverlapsWall => "finish overlaps a wall",
+        }
+    }
+}
+
+pub struct Issue {
+    pub kind: IssueKind,
+    pub pos: vec2<f32>,
+    pub surfaces: Vec<usize>,
+}
+
+impl std::fmt::Display for Issue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} at ({:.3}, {:.3})",
+            self.kind.description(),
+            self.pos.x,
+            self.pos.y,
+        )?;
+        if !self.surfaces.is_empty() {
+            write!(f, ", surfaces {:?}", self.surfaces)?;
+        }
+        Ok(())
+    }
+}
+
+/// Point where two segments cross, not counting shared endpoints
+pub fn segment_intersection(
+    [a1, a2]: [vec2<f32>; 2],
+    [b1, b2]: [vec2<f32>; 2],
+) -> Option<vec2<f32>> {
+    let da = a2 - a1;
+    let db = b2 - b1;
+    let denom = vec2::skew(da, db);
+    if denom.abs() < EPS {
+        return None;
+    }
+    let t = vec2::skew(b1 - a1, db) / denom;
+    let u = vec2::skew(b1 - a1, da) / denom;
+    let range = EPS..=1.0 - EPS;
+    if range.contains(&t) && range.contains(&u) {
+        Some(a1 + da * t)
+    } else {
+        None
+    }
+}
+
+/// Whether the point is in open space, matching the stencil fill of `LevelMesh::insides`:
+/// every surface fans a triangle to the origin, so the origin is open space
+/// and every surface crossed on the way from it toggles
+pub fn is_open_space(level: &Level, p: vec2<f32>) -> bool {
+    !crosses_odd(
+        level.surfaces.iter().map(|surface| surface.ends),
+        vec2::ZERO,
+        p,
+    )
+}
+
+/// Even-odd rule: whether an odd number of segments cross the one from `from` to `to`
+pub fn crosses_odd(
+    segments: impl IntoIterator<Item = [vec2<f32>; 2]>,
+    from: vec2<f32>,
+    to: vec2<f32>,
+) -> bool {
+    let mut odd = false;
+    for [a, b] in segments {
+        let sides_of_segment =
+            (vec2::skew(b - a, from - a) > 0.0) != (vec2::skew(b - a, to - a) > 0.0);
+        let sides_of_ray =
+            (vec2::skew(to - from, a - from) > 0.0) != (vec2::skew(to - from, b - from) > 0.0);
+        if sides_of_segment && sides_of_ray {
+            odd = !odd;
+        }
+    }
+    odd
+}
+
+pub fn validate(config: &Config, level: &Level) -> Vec<Issue> {
+    let mut issues = Vec::new();
+    let same = |a: vec2<f32>, b: vec2<f32>| (a - b).len() < EPS;
+
+    for (index, surface) in level.surfaces.iter().enumerate() {
+        let [a, b] = surface.ends;
+        if same(a, b) {
+            issues.push(Issue {
+                kind: IssueKind::ZeroLength,
+                pos: a,
+                surfaces: vec![index],
+            });
+        }
+    }
+
+    for (i, first) in level.surfaces.iter().enumerate() {
+        for (j, second) in level.surfaces.iter().enumerate().skip(i + 1) {
+            let [a1, a2] = first.ends;
+            let [b1, b2] = second.ends;
+            if (same(a1, b1) && same(a2, b2)) || (same(a1, b2) && same(a2, b1)) {
+                issues.push(Issue {
+                    kind: IssueKind::Duplicate,
+                    pos: (a1 + a2) / 2.0,
+                    surfaces: vec![i, j],
+                });
+                continue;
+            }
+            if let Some(pos) = segment_intersection(first.ends, second.ends) {
+                issues.push(Issue {
+                    kind: IssueKind::SelfIntersection,
+                    pos,
+                    surfaces: vec![i, j],
+                });
+            }
+        }
+    }
+
+    let ends: Vec<(usize, vec2<f32>)> = level
+        .surfaces
+        .iter()
+        .enumerate()
+        .flat_map(|(index, surface)| surface.ends.map(|end| (index, end)))
+        .collect();
+    for (i, &(first, a)) in ends.iter().enumerate() {
+        for &(second, b) in &ends[i + 1..] {
+            let distance = (a - b).len();
+            if first != second && distance >= EPS && distance < config.editor.snap_distance {
+                issues.push(Issue {
+                    kind: IssueKind::NearMiss,
+                    pos: (a + b) / 2.0,
+                    surfaces: vec![first, second],
+                });
+            }
+        }
+    }
+
+    // Every vertex of a closed loop is shared by an even number of surface ends
+    let mut reported: Vec<vec2<f32>> = Vec::new();
+    for &(_, end) in &ends {
+        if reported.iter().any(|&p| same(p, end)) {
+            continue;
+        }
+        let touching: Vec<usize> = ends
+            .iter()
+            .filter(|&&(_, other)| same(other, end))
+            .map(|&(index, _)| index)
+            .collect();
+        if touching.len() % 2 == 1 {
+            reported.push(end);
+            issues.push(Issue {
+                kind: IssueKind::OpenLoop,
+                pos: end,
+                surfaces: touching,
+            });
+        }
+    }
+
+    if !is_open_space(level, level.start_pos)
+        || level
+            .surfaces
+            .iter()
+            .any(|surface| surface.to(level.start_pos).distance < config.player.radius)
+    {
+        issues.push(Issue {
+            kind: IssueKind::StartInsideGeometry,
+            pos: level.start_pos,
+            surfaces: vec![],
+        });
+    }
+
+    let overlapping: Vec<usize> = level
+        .surfaces
+        .iter()
+        .enumerate()
+        .filter(|(_, surface)| surface.to(level.finish_pos).distance < level.finish_radius(config))
+        .map(|(index, _)| index)
+        .collect();
+    if !overlapping.is_empty() || !is_open_space(level, level.finish_pos) {
+        issues.push(Issue {
+            kind: IssueKind::FinishOverlapsWall,
+            pos: level.finish_pos,
+            surfaces: overlapping,
+        });
+    }
+
+    issues
+}
+
+/// Validates every level in the list and prints the issues, returns whether all are valid
+pub fn validate_all(config: &Config, levels: &Levels) -> bool {
+    let mut valid = true;
+    for name in levels
+        .manifest
+        .campaigns
+        .iter()
+        .flat_map(|campaign| &campaign.levels)
+        .map(|level| &level.name)
+    {
+        let issues = validate(config, &levels.map[name]);
+        if issues.is_empty() {
+            println!("{name}: ok");
+            continue;
+        }
+        valid = false;
+        println!("{name}: {} issue(s)", issues.len());
+        for issue in issues {
+            println!("  {issue}");
+        }
+    }
+    valid
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> Config {
+        toml::from_str(include_str!("../assets/config.toml")).unwrap()
+    }
+
+    fn level(surfaces: &[[(f32, f32); 2]], start: (f32, f32), finish: (f32, f32)) -> Level {
+        Level {
+            start_pos: vec2(start.0, start.1),
+            finish_pos: vec2(finish.0, finish.1),
+            finish_radius: None,
+            surfaces: surfaces
+                .iter()
+                .map(|ends| Surface {
+                    ends: ends.map(|(x, y)| vec2(x, y)),
+                })
+                .collect(),
+        }
+    }
+
+    fn square(center: (f32, f32), size: f32) -> Vec<[(f32, f32); 2]> {
+        [
+            [(-1.0, -1.0), (1.0, -1.0)],
+            [(1.0, -1.0), (1.0, 1.0)],
+            [(1.0, 1.0), (-1.0, 1.0)],
+            [(-1.0, 1.0), (-1.0, -1.0)],
+        ]
+        .into_iter()
+        .map(|ends| ends.map(|(x, y)| (center.0 + x * size, center.1 + y * size)))
+        .collect()
+    }
+
+    /// Closed room around the origin, like the shipped levels
+    fn room() -> Vec<[(f32, f32); 2]> {
+        square((0.0, 0.0), 10.0)
+    }
+
+    fn kinds(level: &Level) -> Vec<IssueKind> {
+        validate(&config(), level)
+            .into_iter()
+            .map(|issue| issue.kind)
+            .collect()
+    }
+
+    #[test]
+    fn closed_room_is_valid() {
+        assert_eq!(kinds(&level(&room(), (-5.0, 0.0), (5.0, 0.0))), []);
+    }
+
+    #[test]
+    fn open_space_matches_fill() {
+        let mut surfaces = room();
+        surfaces.extend(square((5.0, 0.0), 1.0));
+        let level = level(&surfaces, (-5.0, 0.0), (5.0, 5.0));
+        assert!(is_open_space(&level, vec2(-5.0, 3.0)));
+        // The way to the origin crosses the pillar twice
+        assert!(is_open_space(&level, vec2(8.0, 0.0)));
+        assert!(!is_open_space(&level, vec2(5.0, 0.5)));
+        assert!(!is_open_space(&level, vec2(20.0, 0.0)));
+    }
+
+    #[test]
+    fn zero_length() {
+        let mut surfaces = room();
+        surfaces.push([(3.0, 3.0), (3.0, 3.0)]);
+        assert!(kinds(&level(&surfaces, (-5.0, 0.0), (5.0, 0.0))).contains(&IssueKind::ZeroLength));
+    }
+
+    #[test]
+    fn duplicate() {
+        let mut surfaces = room();
+        surfaces.push([(10.0, 10.0), (10.0, -10.0)]);
+        assert!(kinds(&level(&surfaces, (-5.0, 0.0), (5.0, 0.0))).contains(&IssueKind::Duplicate));
+    }
+
+    #[test]
+    fn self_intersection() {
+        let mut surfaces = room();
+        surfaces.push([(-3.0, -3.0), (3.0, 3.0)]);
+        surfaces.push([(-3.0, 3.0), (3.0, -3.0)]);
+        assert!(kinds(&level(&surfaces, (-5.0, 0.0), (5.0, 0.0)))
+            .contains(&IssueKind::SelfIntersection));
+    }
+
+    #[test]
+    fn near_miss_and_open_loop() {
+        let mut surfaces = room();
+        surfaces[3][1] = (-10.0, -10.0 + config().editor.snap_distance / 2.0);
+        let kinds = kinds(&level(&surfaces, (-5.0, 0.0), (5.0, 0.0)));
+        assert!(kinds.contains(&IssueKind::NearMiss));
+        assert!(kinds.contains(&IssueKind::OpenLoop));
+    }
+
+    #[test]
+    fn start_inside_geometry() {
+        let kinds = kinds(&level(&room(), (20.0, 0.0), (5.0, 0.0)));
+        assert_eq!(kinds, [IssueKind::StartInsideGeometry]);
+        let kinds = self::kinds(&level(&room(), (-9.99, 0.0), (5.0, 0.0)));
+        assert_eq!(kinds, [IssueKind::StartInsideGeometry]);
+    }
+
+    #[test]
+    fn finish_overlaps_wall() {
+        let kinds = kinds(&level(&room(), (-5.0, 0.0), (9.99, 0.0)));
+        assert_eq!(kinds, [IssueKind::FinishOverlapsWall]);
+    }
+}
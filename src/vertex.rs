@@ -0,0 +1,104 @@
+use super::*;
+
+/// Surface ends closer than this are the same vertex
+const COINCIDENT_DISTANCE: f32 = 1e-4;
+
+/// Surface ends being dragged together
+pub struct Drag {
+    /// (surface index, end index)
+    pub ends: Vec<(usize, usize)>,
+    /// Affected surfaces as they were before the drag
+    pub original: Vec<(usize, Surface)>,
+    /// Split done when the drag started from the middle of a surface
+    pub split: Option<history::Command>,
+}
+
+impl Drag {
+    pub fn new(level: &Level, pos: vec2<f32>, split: Option<history::Command>) -> Self {
+        let ends = ends_at(level, pos);
+        let mut indices: Vec<usize> = ends.iter().map(|&(index, _)| index).collect();
+        indices.dedup();
+        Self {
+            original: indices
+                .into_iter()
+                .map(|index| (index, level.surfaces[index].clone()))
+                .collect(),
+            ends,
+            split,
+        }
+    }
+
+    /// Whether the surface has an end being dragged
+    pub fn moves(&self, surface: usize) -> bool {
+        self.original.iter().any(|&(index, _)| index == surface)
+    }
+}
+
+/// Closest surface end within `max_distance`
+pub fn nearest(level: &Level, pos: vec2<f32>, max_distance: f32) -> Option<vec2<f32>> {
+    level
+        .surfaces
+        .iter()
+        .flat_map(|surface| surface.ends)
+        .filter(|&end| (end - pos).len() < max_distance)
+        .min_by_key(|&end| r32((end - pos).len()))
+}
+
+/// All surface ends at the vertex as (surface index, end index)
+pub fn ends_at(level: &Level, pos: vec2<f32>) -> Vec<(usize, usize)> {
+    level
+        .surfaces
+        .iter()
+        .enumerate()
+        .flat_map(|(index, surface)| {
+            surface
+                .ends
+                .iter()
+                .enumerate()
+                .filter(|(_, &end)| (end - pos).len() < COINCIDENT_DISTANCE)
+                .map(move |(end, _)| (index, end))
+        })
+        .collect()
+}
+
+/// Inserts a vertex at `pos`, the second half is appended as a new surface
+pub fn split(level: &Level, index: usize, pos: vec2<f32>) -> history::Command {
+    let surface = &level.surfaces[index];
+    let [a, b] = surface.ends;
+    history::Command::Batch(vec![
+        history::Command::ModifySurfaces(vec![(
+            index,
+            surface.clone(),
+            Surface { ends: [a, pos] },
+        )]),
+        history::Command::AddSurfaces(vec![Surface { ends: [pos, b] }]),
+    ])
+}
+
+/// Replaces the two segments meeting at the vertex with one,
+/// only if the vertex lies within `tolerance` of the merged segment
+pub fn dissolve(level: &Level, pos: vec2<f32>, tolerance: f32) -> Result<history::Command, String> {
+    let ends = ends_at(level, pos);
+    let [(first, first_end), (second, second_end)] = ends[..] else {
+        return Err(format!(
+            "can only dissolve a vertex joining 2 segments, this one joins {}",
+            ends.len(),
+        ));
+    };
+    if first == second {
+        return Err("segment has zero length".to_owned());
+    }
+    let merged = Surface {
+        ends: [
+            level.surfaces[first].ends[1 - first_end],
+            level.surfaces[second].ends[1 - second_end],
+        ],
+    };
+    if merged.to(pos).distance > tolerance {
+        return Err("segments are not collinear".to_owned());
+    }
+    Ok(history::Command::Batch(vec![
+        history::Command::ModifySurfaces(vec![(first, level.surfaces[first].clone(), merged)]),
+        history::Command::remove_surfaces(level, vec![second]),
+    ]))
+}
@@ -0,0 +1,93 @@
+use super::*;
+
+/// Fov change per unit of mouse wheel delta
+const ZOOM_SENSITIVITY: f32 = 0.002;
+const MIN_FOV: f32 = 1.0;
+const MAX_FOV: f32 = 1000.0;
+/// Extra room around framed objects, relative to their size
+const FRAME_MARGIN: f32 = 0.1;
+/// Pixels around the start and finish handles that grab them, at any zoom
+pub const HANDLE_PICK_RADIUS: f32 = 12.0;
+
+pub fn bounds(points: impl IntoIterator<Item = vec2<f32>>) -> Option<Aabb2<f32>> {
+    let mut points = points.into_iter();
+    let first = points.next()?;
+    Some(points.fold(Aabb2::point(first), |bounds, p| Aabb2 {
+        min: vec2(bounds.min.x.min(p.x), bounds.min.y.min(p.y)),
+        max: vec2(bounds.max.x.max(p.x), bounds.max.y.max(p.y)),
+    }))
+}
+
+/// Surface ends, start and finish
+pub fn level_points(level: &Level) -> impl Iterator<Item = vec2<f32>> + '_ {
+    level
+        .surfaces
+        .iter()
+        .flat_map(|surface| surface.ends)
+        .chain([level.start_pos, level.finish_pos])
+}
+
+/// World length of a distance on screen with a `Camera2dFov::MinSide` fov
+pub fn pixels_to_world(pixels: f32, fov: f32, framebuffer_size: vec2<f32>) -> f32 {
+    pixels * fov / framebuffer_size.x.min(framebuffer_size.y)
+}
+
+/// Center and `Camera2dFov::MinSide` fov showing the whole box
+pub fn frame(bounds: Aabb2<f32>, framebuffer_size: vec2<f32>) -> (vec2<f32>, f32) {
+    let size = bounds.size() * (1.0 + 2.0 * FRAME_MARGIN);
+    let min_side = framebuffer_size.x.min(framebuffer_size.y);
+    let fov = (size.x * min_side / framebuffer_size.x)
+        .max(size.y * min_side / framebuffer_size.y)
+        .clamp(MIN_FOV, MAX_FOV);
+    (bounds.center(), fov)
+}
+
+/// Changes the fov keeping the point under the cursor in place
+pub fn zoom(
+    camera: &mut Camera2d,
+    fov: &mut f32,
+    framebuffer_size: vec2<f32>,
+    cursor: vec2<f32>,
+    delta: f32,
+) {
+    let before = camera.screen_to_world(framebuffer_size, cursor);
+    *fov = (*fov * (-delta * ZOOM_SENSITIVITY).exp()).clamp(MIN_FOV, MAX_FOV);
+    camera.fov = Camera2dFov::MinSide(*fov);
+    let after = camera.screen_to_world(framebuffer_size, cursor);
+    camera.center += before - after;
+}
+
+/// Overview of the level with the visible area, returns the clicked or dragged to position
+pub fn minimap(
+    ui: &mut egui::Ui,
+    level: &Level,
+    camera: &Camera2d,
+    framebuffer_size: vec2<f32>,
+    size: f32,
+) -> Option<vec2<f32>> {
+    let view = [vec2::ZERO, framebuffer_size]
+        .map(|screen_pos| camera.screen_to_world(framebuffer_size, screen_pos));
+    let bounds = bounds(level_points(level).chain(view))?;
+    let (rect, response) =
+        ui.allocate_exact_size(egui::vec2(size, size), egui::Sense::click_and_drag());
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 2.0, ui.visuals().extreme_bg_color);
+    let scale = size * 0.9 / bounds.width().max(bounds.height()).max(1e-3);
+    let center = bounds.center();
+    let to_screen =
+        |p: vec2<f32>| rect.center() + egui::vec2(p.x - center.x, center.y - p.y) * scale;
+    let stroke = egui::Stroke::new(1.0, ui.visuals().text_color());
+    for surface in &level.surfaces {
+        painter.line_segment(surface.ends.map(to_screen), stroke);
+    }
+    painter.circle_filled(to_screen(level.start_pos), 2.0, egui::Color32::GREEN);
+    painter.circle_filled(to_screen(level.finish_pos), 2.0, egui::Color32::RED);
+    painter.rect_stroke(
+        egui::Rect::from_two_pos(to_screen(view[0]), to_screen(view[1])),
+        0.0,
+        egui::Stroke::new(1.0, ui.visuals().selection.bg_fill),
+    );
+    let pos = response.interact_pointer_pos()?;
+    let offset = (pos - rect.center()) / scale;
+    Some(center + vec2(offset.x, -offset.y))
+}